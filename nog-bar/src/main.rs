@@ -1,3 +1,4 @@
+use clap::clap_app;
 use iced::{Application, Color, Command, Container, Row, Text};
 use nog_client::{BarItem, BarItemAlignment, Client};
 use nog_iced::{iced, load_font};
@@ -133,8 +134,37 @@ impl Application for App {
 }
 
 fn main() {
+    let matches = clap_app! (nog_bar =>
+        (version: "1.0")
+        (author: "Tim Untersberger <timuntersberger2@gmail.com")
+        (about: "The appbar of nog")
+        (@arg X: -x +takes_value "The x position of the bar. (Default: 0)")
+        (@arg Y: -y +takes_value "The y position of the bar. (Default: 0)")
+        (@arg WIDTH: -W --width +takes_value "The width of the bar. (Default: 1920)")
+        (@arg HEIGHT: -H --height +takes_value "The height of the bar. (Default: the bar height of the config)")
+    )
+    .get_matches();
+
     let mut client = Client::connect("localhost:8080".into(), None).unwrap();
     let bar_content = client.get_bar_content().unwrap();
+
+    let x = matches
+        .value_of("X")
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(0);
+    let y = matches
+        .value_of("Y")
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(0);
+    let width = matches
+        .value_of("WIDTH")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(1920);
+    let height = matches
+        .value_of("HEIGHT")
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(bar_content.height as u32);
+
     let font: &'static [u8] = Box::leak(Box::new(
         (*load_font(bar_content.font_name)
             .or_else(|| load_font(String::from("Consolas")))
@@ -145,8 +175,8 @@ fn main() {
         window: iced::window::Settings {
             always_on_top: true,
             decorations: false,
-            position: iced::window::Position::Specific(0, 0),
-            size: (1920, bar_content.height as u32),
+            position: iced::window::Position::Specific(x, y),
+            size: (width, height),
             ..Default::default()
         },
         id: None,
@@ -2,7 +2,7 @@ use std::io;
 
 use clap::clap_app;
 use crossterm::terminal::enable_raw_mode;
-use nog_client::{json, Client, ClientError};
+use nog_client::{json, Client, ClientError, Stats};
 use tui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
@@ -29,6 +29,12 @@ fn main() {
             (version: "1.0")
             (author: "Tim Untersberger <timuntersberger2@gmail.com")
         )
+        (@subcommand stats =>
+            (about: "Prints how long the applications had focus")
+            (version: "1.0")
+            (author: "Tim Untersberger <timuntersberger2@gmail.com")
+            (@arg DAYS: -d --days +takes_value "The amount of days to print, including today. (Default: 1)")
+        )
         (@subcommand render =>
             (about: "Tries to render the currently managed windows in the terminal")
             (version: "1.0")
@@ -76,6 +82,20 @@ fn main() {
                 json::to_string_pretty(&client.get_state().unwrap()).unwrap()
             );
         }
+        ("stats", Some(m)) => {
+            let days = match m.value_of("DAYS").unwrap_or("1").parse::<u32>() {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    return;
+                }
+            };
+
+            match client.get_stats(days) {
+                Ok(stats) => print_stats(&stats),
+                Err(e) => eprintln!("error: {:?}", e),
+            };
+        }
         ("bar", Some(m)) => todo!(),
         ("render", Some(m)) => tui(),
         ("render_bar", Some(m)) => todo!(),
@@ -83,6 +103,25 @@ fn main() {
     }
 }
 
+fn print_stats(stats: &Stats) {
+    for day in &stats.days {
+        println!("{}", day.date);
+
+        for ws in &day.workspaces {
+            println!("  Workspace {}", ws.workspace_id);
+
+            for app in &ws.apps {
+                println!(
+                    "    {:<32} {:>3}h {:>2}m",
+                    app.app,
+                    app.seconds / 3600,
+                    app.seconds / 60 % 60
+                );
+            }
+        }
+    }
+}
+
 fn tui() {
     enable_raw_mode().unwrap();
     let stdout = io::stdout();
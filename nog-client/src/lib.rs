@@ -1,4 +1,4 @@
-pub use nog_protocol::{json, BarContent, BarItem, BarItemAlignment, Message, State};
+pub use nog_protocol::{json, BarContent, BarItem, BarItemAlignment, Message, State, Stats};
 pub use std::net::ToSocketAddrs;
 use std::{
     io::{self, Read, Write},
@@ -88,4 +88,13 @@ impl Client {
 
         json::from_str(&response).map_err(|_| ClientError::InvalidResponse(response))
     }
+
+    /// Returns the stats of the last `days` days, including today
+    pub fn get_stats(&mut self, days: u32) -> ClientResult<Stats> {
+        let response = self
+            .send_message(&Message::GetStats { days })
+            .map_err(ClientError::IoError)?;
+
+        json::from_str(&response).map_err(|_| ClientError::InvalidResponse(response))
+    }
 }
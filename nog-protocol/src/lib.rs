@@ -28,11 +28,15 @@ pub struct BarContent {
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Window {
     pub id: usize,
+    /// Whether the window tried to get focus while its workspace wasn't focused
+    pub urgent: bool,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Workspace {
     pub id: usize,
+    /// The name that gets displayed in the bar
+    pub name: String,
     pub layout: String,
     pub focused_window_id: Option<usize>,
     pub windows: Vec<Window>,
@@ -44,6 +48,8 @@ pub struct Display {
     pub monitor_id: usize,
     pub focused_workspace_id: Option<usize>,
     pub workspaces: Vec<Workspace>,
+    /// Windows that are visible on every workspace of the display
+    pub sticky_windows: Vec<Window>,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -52,11 +58,38 @@ pub struct State {
     pub displays: Vec<Display>,
 }
 
+/// How long the windows of an application had focus
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct AppStats {
+    pub app: String,
+    pub seconds: u64,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceStats {
+    pub workspace_id: usize,
+    pub apps: Vec<AppStats>,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct DayStats {
+    /// Formatted as `YYYY-MM-DD`
+    pub date: String,
+    pub workspaces: Vec<WorkspaceStats>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Stats {
+    /// Beginning with the most recent day
+    pub days: Vec<DayStats>,
+}
+
 #[derive(Debug)]
 pub enum Message {
     ExecuteLua { code: String, print_type: bool },
     GetBarContent,
     GetState,
+    GetStats { days: u32 },
 }
 
 #[derive(Debug)]
@@ -72,6 +105,7 @@ impl Message {
             ExecuteLua { code, print_type } => format!("ExecuteLua:{}:{}", print_type, code),
             GetBarContent => String::from("GetBarContent:"),
             GetState => String::from("GetState:"),
+            GetStats { days } => format!("GetStats:{}", days),
         }
         .as_bytes()
         .to_vec();
@@ -99,6 +133,11 @@ impl Message {
             },
             ("GetBarContent", _) => Ok(Message::GetBarContent),
             ("GetState", _) => Ok(Message::GetState),
+            ("GetStats", days) => Ok(Message::GetStats {
+                days: days
+                    .parse::<u32>()
+                    .map_err(|_| DeserializeError::InvalidFormat)?,
+            }),
             _ => Err(DeserializeError::InvalidFormat),
         }
     }
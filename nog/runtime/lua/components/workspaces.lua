@@ -15,6 +15,8 @@ return function()
 
         if nog.dsp_get_focused_ws(dsp) == ws_id then
           bg = nog.scale_color(nog.config.color, 2.0)
+        elseif nog.ws_is_urgent(ws_id) then
+          bg = nog.config.urgent_color
         end
 
         return {
@@ -26,6 +26,10 @@ function nog.__organize(ws_id, layout_name)
   return ws_to_layout[ws_id]
 end
 
+function nog.__reset_layout(ws_id)
+  ws_to_layout[ws_id] = nil
+end
+
 nog.execute_runtime_file "keybindings.lua"
 nog.execute_runtime_file "package_loader.lua"
 
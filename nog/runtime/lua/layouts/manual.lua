@@ -1,8 +1,15 @@
 return function()
-  return function(graph, event, win_id)
-    if event == "created" then
-      graph:add_window_node(nil, win_id)
-    elseif event == "deleted" or event == "minimized" then
+  -- A numeric slot is the position the window gets inserted at
+  return function(graph, event, win_id, slot)
+    if event == "created" or event == "managed" then
+      local id = graph:add_window_node(nil, win_id)
+      if type(slot) == "number" then
+        local children = graph:get_children(nil)
+        for i = #children - 1, slot, -1 do
+          graph:swap_nodes(id, children[i])
+        end
+      end
+    elseif event == "deleted" or event == "minimized" or event == "unmanaged" then
       graph:del_window_node(win_id)
     end
   end
@@ -14,17 +14,43 @@ return function()
     )
   end
 
+  -- Adds the window to the end of the slaves
+  function state.add_slave(self, graph, win_id)
+    if #self.slaves == 0 then
+      self.slave_group = graph:add_column_node(nil)
+    end
+    local id = graph:add_window_node(self.slave_group, win_id)
+    table.insert(self.slaves, id)
+    return id
+  end
+
+  -- Moves the last slave to the given position by swapping it with the slaves in front of it
+  function state.move_last_slave(self, graph, pos)
+    local id = table.remove(self.slaves)
+    for i = #self.slaves, pos, -1 do
+      graph:swap_nodes(id, self.slaves[i])
+    end
+    table.insert(self.slaves, pos, id)
+  end
+
+  -- The slot is either "master", "slave" or the position the window gets inserted at, where the
+  -- first position is the master
   return function(graph, event, win_id, extra)
     if event == "created" or event == "managed" then
-      local slave_count = #state.slaves
       if state.master == nil then
         state.master = graph:add_window_node(nil, win_id)
+      elseif extra == "master" or extra == 1 then
+        -- The previous master becomes the first slave
+        local id = state:add_slave(graph, win_id)
+        graph:swap_nodes(state.master, id)
+        state.slaves[#state.slaves] = state.master
+        state.master = id
+        state:move_last_slave(graph, 1)
       else
-        if slave_count == 0 then
-          state.slave_group = graph:add_column_node(nil)
+        state:add_slave(graph, win_id)
+        if type(extra) == "number" then
+          state:move_last_slave(graph, math.min(extra - 1, #state.slaves))
         end
-        local id = graph:add_window_node(state.slave_group, win_id)
-        table.insert(state.slaves, id)
       end
     elseif event == "deleted" or event == "minimized" or event == "unmanaged" then
       local deleted_id = graph:del_window_node(win_id)
@@ -3,23 +3,26 @@ use std::{mem, str::FromStr, sync::Arc};
 use crate::{
     bar::Bar,
     config::{Config, ConfigProperty},
+    direction::Direction,
+    display::{DisplayId, DisplayTarget},
     event::Event,
-    graph::GraphNode,
     key::Key,
     key_combination::KeyCombination,
     keybinding::{Keybinding, KeybindingMode},
     keybinding_event_loop::KeybindingEventLoop,
+    launch::LaunchOptions,
     lua::LuaRuntime,
     modifiers::Modifiers,
     notification::{Notification, NotificationManager},
     platform::{Api, NativeApi, NativeWindow, Window, WindowId},
     session,
     state::State,
+    template::{self, PendingSlot},
     workspace::WorkspaceId,
 };
-use log::info;
+use log::{error, info, warn};
 use mlua::FromLua;
-pub use window::WindowAction;
+pub use window::{manage_window, WindowAction};
 pub use workspace::WorkspaceAction;
 
 mod window;
@@ -51,7 +54,8 @@ action_fn!(ExecuteLuaActionFn, mlua::Result<String>);
 pub enum Action {
     Launch(String),
     SaveSession(String),
-    LoadSession(String),
+    /// Whether to launch the programs of windows that are missing
+    LoadSession(String, bool),
     ShowTaskbars,
     HideTaskbars,
     ShowBars,
@@ -60,6 +64,9 @@ pub enum Action {
     Hibernate,
     CreateNotification(Notification),
     MoveWindowToWorkspace(Option<WindowId>, WorkspaceId),
+    MoveWindowToDisplay(Option<WindowId>, DisplayTarget),
+    MoveWorkspaceToDisplay(Option<WorkspaceId>, DisplayTarget),
+    FocusDisplay(Direction),
     SimulateKeyPress {
         key: Key,
         modifiers: Modifiers,
@@ -90,9 +97,9 @@ impl std::fmt::Display for Action {
             "{}",
             match self {
                 Action::CreateNotification(n) => String::from("Create notification"),
-                Action::Launch(path) => format!("Launch '{}'", path),
+                Action::Launch(cmd) => format!("Launch '{}'", cmd),
                 Action::SaveSession(name) => format!("Save session as '{}'", name),
-                Action::LoadSession(name) => format!("Load session '{}'", name),
+                Action::LoadSession(name, _) => format!("Load session '{}'", name),
                 Action::ShowTaskbars => format!("Show taskbars"),
                 Action::HideTaskbars => format!("Hide taskbars"),
                 Action::ShowBars => format!("Show bars"),
@@ -101,6 +108,11 @@ impl std::fmt::Display for Action {
                 Action::Hibernate => format!("Hibernate"),
                 Action::MoveWindowToWorkspace(window, workspace) =>
                     format!("Move Window({:?}) to Workspace({:?})", window, workspace),
+                Action::MoveWindowToDisplay(window, display) =>
+                    format!("Move Window({:?}) to Display({:?})", window, display),
+                Action::MoveWorkspaceToDisplay(workspace, display) =>
+                    format!("Move Workspace({:?}) to Display({:?})", workspace, display),
+                Action::FocusDisplay(direction) => format!("Focusing Display {}", direction),
                 Action::SimulateKeyPress { key, modifiers } => format!(
                     "Simulate '{}'",
                     KeyCombination::new(key.clone(), modifiers.clone())
@@ -139,7 +151,11 @@ impl Action {
             _ => log::trace!("{}", &self),
         }
         match self {
-            Action::Launch(path) => Api::launch(path),
+            Action::Launch(cmd) => {
+                if let Err(e) = Api::launch(&cmd, &LaunchOptions::default()) {
+                    error!("Failed to launch '{}': {}", cmd, e);
+                }
+            }
             Action::MoveWindowToWorkspace(win_id, ws_id) => {
                 let win_id = win_id.or_else(|| {
                     state.with_focused_dsp(|dsp| {
@@ -155,12 +171,159 @@ impl Action {
                     WindowAction::Manage(Some(ws_id), Some(win_id)).handle(state, rt);
                 }
             }
+            Action::MoveWindowToDisplay(win_id, target) => {
+                let win_id = win_id.or_else(|| {
+                    state.with_focused_dsp(|dsp| {
+                        dsp.wm
+                            .get_focused_workspace()
+                            .get_focused_win()
+                            .map(|x| x.get_id())
+                    })
+                });
+
+                let win_id = match win_id {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let src_id = match state.with_dsp_containing_win_mut(win_id, |d| d.id.clone()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let dst_id = match state.resolve_dsp_target(&src_id, target) {
+                    Some(x) if x != src_id => x,
+                    _ => return,
+                };
+
+                let free_ws_id = state.get_free_ws_id();
+
+                let unmanaged = state.with_dsp_mut(src_id.clone(), |d| {
+                    let config = state.get_display_config(&d.id);
+                    let area = d.get_render_area(&config);
+                    let ws_id =
+                        d.wm.workspaces
+                            .iter()
+                            .find(|ws| ws.has_window(win_id))
+                            .map(|ws| ws.id);
+                    let floating = d.wm.workspaces.iter().any(|ws| ws.is_floating(win_id));
+                    d.wm.unmanage(rt, &config, area, win_id)
+                        .map(|_| (ws_id, floating))
+                });
+
+                let (src_ws_id, floating) = match unmanaged {
+                    Some(Ok(x)) => x,
+                    Some(Err(e)) => {
+                        error!("Failed to move the window to another display: {:?}", e);
+                        return;
+                    }
+                    None => (None, false),
+                };
+
+                let moved = state.with_dsp_mut(dst_id, |d| {
+                    let config = state.get_display_config(&d.id);
+
+                    if d.wm.focused_workspace_id.is_none() {
+                        d.wm.change_workspace(rt, &config, free_ws_id);
+                    }
+
+                    let area = d.get_render_area(&config);
+                    let win = Window::new(win_id);
+
+                    let result = if floating {
+                        d.wm.float(&config, None, win);
+                        Ok(())
+                    } else {
+                        d.wm.manage(rt, &config, None, area, win, None)
+                    };
+
+                    d.wm.render(&config, area);
+                    result
+                });
+
+                // The window is no longer managed by any display at this point, which is why it
+                // gets put back where it came from.
+                if let Some(Err(e)) = moved {
+                    error!("Failed to move the window to another display: {:?}", e);
+
+                    state.with_dsp_mut(src_id, |d| {
+                        let config = state.get_display_config(&d.id);
+                        let area = d.get_render_area(&config);
+                        let win = Window::new(win_id);
+
+                        if let Err(e) = d.wm.manage(rt, &config, src_ws_id, area, win, None) {
+                            error!("Failed to manage the window again: {:?}", e);
+                        }
+
+                        d.wm.render(&config, area);
+                    });
+                }
+            }
+            Action::MoveWorkspaceToDisplay(ws_id, target) => {
+                let ws_id = match ws_id.or_else(|| state.get_focused_ws_id()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let src_id = match state.with_dsp_containing_ws_mut(ws_id, |d| d.id.clone()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let dst_id = match state.resolve_dsp_target(&src_id, target) {
+                    Some(x) if x != src_id => x,
+                    _ => return,
+                };
+
+                let free_ws_id = state.get_free_ws_id();
+
+                let taken = state
+                    .with_dsp_mut(src_id, |d| {
+                        let config = state.get_display_config(&d.id);
+                        let taken = d.wm.take_workspace(rt, &config, ws_id, free_ws_id);
+                        d.wm.render(&config, d.get_render_area(&config));
+                        taken
+                    })
+                    .flatten();
+
+                if let Some((ws, window_cleanup)) = taken {
+                    state.with_dsp_mut(dst_id.clone(), |d| {
+                        let config = state.get_display_config(&d.id);
+                        d.wm.insert_workspace(rt, &config, ws, window_cleanup);
+                        d.wm.render(&config, d.get_render_area(&config));
+                    });
+
+                    state.set_focused_dsp_id(dst_id);
+                }
+            }
+            Action::FocusDisplay(dir) => {
+                let src_id = state.get_focused_dsp_id();
+
+                if let Some(dst_id) = state.get_dsp_in_direction(&src_id, dir) {
+                    let free_ws_id = state.get_free_ws_id();
+
+                    let win = state
+                        .with_dsp_mut(dst_id.clone(), |d| {
+                            if d.wm.focused_workspace_id.is_none() {
+                                let config = state.get_display_config(&d.id);
+                                d.wm.change_workspace(rt, &config, free_ws_id);
+                            }
+
+                            d.wm.get_focused_workspace().get_focused_win()
+                        })
+                        .flatten();
+
+                    state.set_focused_dsp_id(dst_id);
+
+                    if let Some(win) = win {
+                        win.focus();
+                    }
+                }
+            }
             Action::Awake => {
                 info!("Awoke!");
 
-                if state.config.read().display_app_bar {
-                    state.tx.send(Event::Action(Action::ShowBars)).unwrap();
-                }
+                state.tx.send(Event::Action(Action::ShowBars)).unwrap();
 
                 if state.config.read().remove_task_bar {
                     state.tx.send(Event::Action(Action::HideTaskbars)).unwrap();
@@ -172,7 +335,36 @@ impl Action {
                     }
                 }
 
-                state.with_focused_dsp_mut(|dsp| dsp.wm.change_workspace(&rt, WorkspaceId(1)));
+                for d in state.displays.write().iter_mut() {
+                    let config = state.get_display_config(&d.id);
+                    let area = d.get_render_area(&config);
+                    if let Err(e) = d.wm.resume(rt, &config, area) {
+                        error!("Failed to resume the display {}: {:?}", d.id.0, e);
+                    }
+                }
+
+                // The workspaces survive hibernation, so there only is no focused workspace on
+                // the first awake.
+                if state
+                    .with_focused_dsp(|d| d.wm.focused_workspace_id)
+                    .is_none()
+                {
+                    WorkspaceAction::Change(WorkspaceId(1)).handle(state, rt);
+                }
+
+                let persistent_ws_ids = state
+                    .config
+                    .read()
+                    .workspaces
+                    .iter()
+                    .filter(|(_, definition)| definition.persistent)
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<_>>();
+
+                for id in persistent_ws_ids {
+                    WorkspaceAction::ApplyDefinition(id).handle(state, rt);
+                }
+
                 state.awake();
             }
             Action::SimulateKeyPress { key, modifiers } => {
@@ -182,9 +374,12 @@ impl Action {
                 state.tx.send(Event::Action(Action::HideBars)).unwrap();
                 state.tx.send(Event::Action(Action::ShowTaskbars)).unwrap();
                 for d in state.displays.write().iter_mut() {
-                    d.wm.cleanup();
+                    d.wm.suspend();
                 }
 
+                state.stats.write().stop();
+                state.stats.write().flush();
+
                 for kb in state.keybindings.read().iter() {
                     if kb.mode != KeybindingMode::Global {
                         KeybindingEventLoop::remove_keybinding(kb.get_id());
@@ -196,29 +391,207 @@ impl Action {
             Action::Window(action) => action.handle(state, rt),
             Action::Workspace(action) => action.handle(state, rt),
             Action::SaveSession(name) => {
-                session::save_session(&name, &state.displays.read()[0].wm.workspaces);
+                let focused_dsp_id = state.get_focused_dsp_id();
+                let session = session::Session::new(
+                    &state.displays.read(),
+                    &focused_dsp_id.0,
+                    &state.config.read().workspace_order,
+                    &state.launch_commands.read(),
+                );
+
+                if let Err(e) = session::save_session(&name, &session) {
+                    error!("Failed to save the session '{}': {}", name, e);
+                }
             }
-            Action::LoadSession(name) => state.with_focused_dsp_mut(|d| {
-                d.wm.workspaces = session::load_session(&name).unwrap();
-                let area = d.get_render_area(&state.config.read());
+            Action::LoadSession(name, launch) => {
+                let session = match session::load_session(&name) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error!("Failed to load the session '{}': {}", name, e);
+                        return;
+                    }
+                };
+
+                if !session.workspace_order.is_empty() {
+                    state.set_ws_order(
+                        session
+                            .workspace_order
+                            .iter()
+                            .map(|id| WorkspaceId(*id))
+                            .collect(),
+                    );
+                }
 
-                let mut ws_windows = Vec::new();
+                let mut window_ids = session.match_windows(&Api::get_windows());
+                let mut pending_slots = Vec::new();
 
-                for ws in &d.wm.workspaces {
-                    for node in ws.graph.nodes.values() {
-                        if let GraphNode::Window(win_id) = node {
-                            ws_windows.push((ws.id, Window::new(*win_id)));
+                // The windows keep the command of the session, so that saving the session again
+                // doesn't lose it
+                for (id, win_id) in &window_ids {
+                    if let Some(command) = session.get_window(*id).and_then(|w| w.launch_command())
+                    {
+                        state.launch_commands.write().insert(*win_id, command);
+                    }
+                }
+
+                // The slots of missing windows get reserved, so that the windows end up in the
+                // right place once they show up. This includes programs that failed to launch,
+                // because the user can still start them manually.
+                for (ws_id, id) in session.get_layout_windows() {
+                    let hints = match session.get_window(id) {
+                        Some(x) if !window_ids.contains_key(&id) => x,
+                        _ => continue,
+                    };
+
+                    let pid = if launch {
+                        let options = LaunchOptions {
+                            args: hints.args.clone(),
+                            ..Default::default()
+                        };
+
+                        match Api::launch(hints.get_cmd(), &options) {
+                            Ok(pid) => pid,
+                            Err(e) => {
+                                error!("Failed to launch '{}': {}", hints.get_cmd(), e);
+                                None
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    let placeholder = template::create_placeholder();
+                    window_ids.insert(id, placeholder);
+                    pending_slots.push(
+                        PendingSlot::new(
+                            ws_id,
+                            placeholder,
+                            pid,
+                            Some(hints.matcher()),
+                            hints.launch_command(),
+                        )
+                        .session(&name, hints.to_string()),
+                    );
+                }
+
+                for id in session.get_window_ids() {
+                    if window_ids.contains_key(&id) {
+                        continue;
+                    }
+
+                    match session.get_window(id) {
+                        Some(hints) => {
+                            warn!("No window matches {} of the session '{}'", hints, name)
                         }
+                        None => warn!(
+                            "Window {} of the session '{}' doesn't exist anymore",
+                            id, name
+                        ),
                     }
                 }
 
-                for (ws_id, window) in ws_windows {
-                    d.wm.manage(rt, &state.config.read(), Some(ws_id), area, window)
-                        .unwrap();
+                let focused_dsp_id = state.get_focused_dsp_id();
+
+                // The workspaces of displays that aren't connected end up on the focused display,
+                // which is why the displays get grouped by their target before loading them.
+                // Otherwise loading one display would throw away what another one just loaded.
+                let mut targets: Vec<(DisplayId, Vec<&session::SessionDisplay>)> = Vec::new();
+
+                for dsp in &session.displays {
+                    let dsp_id = dsp
+                        .id
+                        .clone()
+                        .map(DisplayId)
+                        .filter(|id| state.with_dsp(id.clone(), |_| ()).is_some())
+                        .unwrap_or_else(|| focused_dsp_id.clone());
+
+                    match targets.iter_mut().find(|(id, _)| *id == dsp_id) {
+                        Some((_, displays)) => displays.push(dsp),
+                        None => targets.push((dsp_id, vec![dsp])),
+                    }
                 }
 
-                d.wm.render(&state.config.read(), area);
-            }),
+                // Displays that aren't part of the session keep their workspaces
+                let taken: Vec<WorkspaceId> = state
+                    .displays
+                    .read()
+                    .iter()
+                    .filter(|d| targets.iter().all(|(id, _)| *id != d.id))
+                    .flat_map(|d| d.wm.workspaces.iter().map(|ws| ws.id))
+                    .collect();
+
+                for (dsp_id, displays) in targets {
+                    let config = state.get_display_config(&dsp_id);
+                    let mut workspaces = Vec::new();
+                    let mut sticky_windows = Vec::new();
+                    let mut focused_ws_id = None;
+
+                    for dsp in displays {
+                        for ws in &dsp.workspaces {
+                            if taken.contains(&WorkspaceId(ws.id)) {
+                                warn!(
+                                    "Workspace {} of the session '{}' is already on another display",
+                                    ws.id, name
+                                );
+                                pending_slots.retain(|slot| slot.ws_id != WorkspaceId(ws.id));
+                                continue;
+                            }
+
+                            workspaces.push(ws.to_workspace(|id| window_ids.get(&id).copied()));
+                        }
+
+                        sticky_windows.extend(
+                            dsp.sticky_windows
+                                .iter()
+                                .filter_map(|id| window_ids.get(&WindowId(*id)).copied()),
+                        );
+
+                        // The display that belongs to the target decides which workspace gets
+                        // focused
+                        if focused_ws_id.is_none() || dsp.id.as_ref() == Some(&dsp_id.0) {
+                            focused_ws_id =
+                                dsp.focused_workspace_id.map(WorkspaceId).or(focused_ws_id);
+                        }
+                    }
+
+                    state.with_dsp_mut(dsp_id, |d| {
+                        let area = d.get_render_area(&config);
+
+                        if let Err(e) = d.wm.load_workspaces(
+                            rt,
+                            &config,
+                            area,
+                            workspaces,
+                            sticky_windows,
+                            focused_ws_id,
+                        ) {
+                            error!("Failed to load the session '{}': {:?}", name, e);
+                        }
+                    });
+                }
+
+                if !pending_slots.is_empty() {
+                    info!(
+                        "Waiting for {} windows of the session '{}'",
+                        pending_slots.len(),
+                        name
+                    );
+                    state.pending_slots.write().extend(pending_slots);
+                }
+
+                if let Some(id) = session.focused_display_id.map(DisplayId) {
+                    if state.with_dsp(id.clone(), |_| ()).is_some() {
+                        state.set_focused_dsp_id(id);
+                    }
+                }
+
+                if state
+                    .with_focused_dsp(|d| d.wm.focused_workspace_id)
+                    .is_none()
+                {
+                    WorkspaceAction::Change(WorkspaceId(1)).handle(state, rt);
+                }
+            }
             Action::ShowTaskbars => {
                 for d in state.displays.write().iter_mut() {
                     d.show_taskbar();
@@ -231,41 +604,63 @@ impl Action {
             }
             Action::UpdateConfig(prop) => {
                 if state.is_awake() {
-                    let event = match prop {
+                    let events = match prop {
                         ConfigProperty::FontSize(_)
                         | ConfigProperty::FontName(_)
                         | ConfigProperty::BarHeight(_) => {
-                            Some(Event::BatchAction(vec![Action::HideBars, Action::ShowBars]))
+                            vec![Event::BatchAction(vec![Action::HideBars, Action::ShowBars])]
                         }
+                        ConfigProperty::Display(_) => vec![
+                            Event::BatchAction(vec![Action::HideBars, Action::ShowBars]),
+                            Event::RenderGraph,
+                        ],
+                        ConfigProperty::Workspace(id) => vec![Event::Action(Action::Workspace(
+                            WorkspaceAction::ApplyDefinition(id),
+                        ))],
                         ConfigProperty::OuterGap(_) | ConfigProperty::InnerGap(_) => {
-                            Some(Event::RenderGraph)
+                            vec![Event::RenderGraph]
                         }
                         ConfigProperty::RemoveTaskBar(old_value) => {
                             match old_value != state.config.read().remove_task_bar {
-                                true => Some(match old_value {
+                                true => vec![match old_value {
                                     true => Event::Action(Action::ShowTaskbars),
                                     false => Event::Action(Action::HideTaskbars),
-                                }),
-                                false => None,
+                                }],
+                                false => vec![],
                             }
                         }
                         ConfigProperty::DisplayAppBar(old_value) => {
                             match old_value != state.config.read().display_app_bar {
-                                true => Some(match old_value {
-                                    true => Event::Action(Action::HideBars),
-                                    false => Event::Action(Action::ShowBars),
-                                }),
-                                false => None,
+                                // Displays can override this value, so we let `ShowBars` figure
+                                // out which bars to show or hide.
+                                true => vec![Event::Action(Action::ShowBars)],
+                                false => vec![],
                             }
                         }
+                        ConfigProperty::WorkspaceNaming(_) | ConfigProperty::AppNames(_) => {
+                            for d in state.displays.write().iter_mut() {
+                                let config = state.get_display_config(&d.id);
+                                d.wm.update_ws_names(&config);
+                            }
+
+                            vec![]
+                        }
                         ConfigProperty::LightTheme(_)
                         | ConfigProperty::Color(_)
                         | ConfigProperty::MultiMonitor(_)
                         | ConfigProperty::RemoveDecorations(_)
-                        | ConfigProperty::IgnoreFullscreenActions(_) => None,
+                        | ConfigProperty::IgnoreFullscreenActions(_)
+                        | ConfigProperty::WorkspaceAutoBackAndForth(_)
+                        | ConfigProperty::WorkspaceSwitchStrategy(_)
+                        | ConfigProperty::MinimizeBehaviour(_)
+                        | ConfigProperty::SwallowParents(_)
+                        | ConfigProperty::FocusStealing(_)
+                        | ConfigProperty::LayoutMaxWindows(_)
+                        | ConfigProperty::UrgentColor(_)
+                        | ConfigProperty::DefaultLayout(_) => vec![],
                     };
 
-                    if let Some(event) = event {
+                    for event in events {
                         state.tx.send(event).unwrap();
                     }
                 }
@@ -364,8 +759,15 @@ impl Action {
             }
             Action::ShowBars => {
                 for d in state.displays.write().iter_mut() {
-                    if d.bar.is_none() {
-                        d.bar = Some(Bar::new().unwrap());
+                    let config = state.get_display_config(&d.id);
+                    let display_app_bar = config.display_app_bar;
+
+                    if display_app_bar && d.bar.is_none() {
+                        d.bar = Some(Bar::new(d.get_bar_area(&config)).unwrap());
+                    } else if !display_app_bar {
+                        if let Some(mut bar) = mem::take(&mut d.bar) {
+                            bar.close();
+                        }
                     }
                 }
             }
@@ -1,9 +1,10 @@
-use log::info;
+use log::{error, info};
 
 use crate::{
     event::Event,
-    lua::{self, LuaEvent, LuaRuntime},
+    lua::{self, lua_error_to_string, LuaEvent, LuaRuntime, ManageRedirect},
     platform::{Api, NativeApi, NativeWindow, Window, WindowId},
+    rule::{self, RuleAction},
     state::State,
     window_event_loop::{WindowEvent, WindowEventKind},
     workspace::WorkspaceId,
@@ -16,6 +17,12 @@ pub enum WindowAction {
     Unmanage(Option<WindowId>),
     Close(Option<WindowId>),
     Minimize(Option<WindowId>),
+    SetSticky(Option<WindowId>, bool),
+    /// Focuses the window of the focused workspace that had focus before the focused window
+    FocusLast,
+    /// Cycles through the windows of the focused display, ordered by when they got focused.
+    /// Reverses the direction if the value is true.
+    CycleMru(bool),
 }
 
 impl std::fmt::Display for WindowAction {
@@ -30,6 +37,13 @@ impl std::fmt::Display for WindowAction {
                 WindowAction::Unmanage(id) => format!("Unmanaging Window({:?})", id),
                 WindowAction::Close(id) => format!("Closing Window({:?})", id),
                 WindowAction::Minimize(id) => format!("Minimizing Window({:?})", id),
+                WindowAction::SetSticky(id, sticky) =>
+                    format!("Setting sticky of Window({:?}) to {}", id, sticky),
+                WindowAction::FocusLast => String::from("Focusing the last focused Window"),
+                WindowAction::CycleMru(reverse) => format!(
+                    "Cycling through the most recently used Windows (reverse: {})",
+                    reverse
+                ),
             }
         )
     }
@@ -65,17 +79,8 @@ impl WindowAction {
 
                 info!("Minimizing '{}'", win.get_title());
 
+                // The resulting event gets handled like the user minimized the window
                 win.minimize();
-
-                state
-                    .tx
-                    .send(Event::Window(WindowEvent {
-                        // TODO: Right now Minimized is not getting handled. Once we handle the
-                        // minimized event correctly change from deleted to minimized.
-                        kind: WindowEventKind::Deleted,
-                        window: win,
-                    }))
-                    .unwrap();
             }
             WindowAction::Manage(ws_id, maybe_win_id) => {
                 let win = maybe_win_id
@@ -92,49 +97,198 @@ impl WindowAction {
                     return;
                 }
 
-                lua::emit_win_manage(
+                let redirect = match lua::emit_win_manage(
                     &rt,
                     LuaEvent::WinManage {
                         manual: true,
                         ws_id: Some(ws_id),
                         win_id: win.get_id(),
                     },
-                )
-                .unwrap();
+                ) {
+                    Ok(Some(redirect)) => redirect,
+                    Ok(None) => {
+                        info!("Managing '{}' got cancelled", win.get_title());
+                        return;
+                    }
+                    // A broken handler shouldn't keep the window from getting managed
+                    Err(e) => {
+                        error!("{}", lua_error_to_string(e));
+                        ManageRedirect::default()
+                    }
+                };
+
+                // The user explicitly wants to manage the window, which is why an ignore rule
+                // doesn't apply here.
+                let rule_action = rule::find_action(&state.rules.read(), &win)
+                    .filter(|action| action != &RuleAction::Ignore);
 
-                state.with_dsp_mut(state.get_focused_dsp_id(), |d| {
-                    d.wm.change_workspace(&rt, ws_id);
+                manage_window(
+                    state,
+                    rt,
+                    win,
+                    redirect.ws_id.or(Some(ws_id)),
+                    redirect.apply_to_rule_action(rule_action),
+                );
+            }
+            WindowAction::FocusLast => state.with_focused_dsp_mut(|d| {
+                let ws = d.wm.get_focused_workspace_mut();
+                let focused_id = ws.get_focused_win().map(|win| win.get_id());
 
-                    let area = d.get_render_area(&state.config.read());
-                    let workspace = d.wm.get_ws_by_id(ws_id).unwrap();
+                if let Some(id) = ws.get_last_focused_win(focused_id) {
+                    ws.focus_window(id).ok();
+                    Window::new(id).focus();
+                }
+            }),
+            WindowAction::CycleMru(reverse) => state.with_focused_dsp_mut(|d| {
+                let config = state.get_display_config(&d.id);
 
-                    if win.exists() && !workspace.has_window(win.get_id()) {
-                        info!("'{}' managed", win.get_title());
+                if let Some(id) = d.wm.cycle_mru(reverse) {
+                    d.wm.focus_window(rt, &config, id);
+                    Window::new(id).focus();
+                }
+            }),
+            WindowAction::SetSticky(maybe_win_id, sticky) => {
+                let win_id = maybe_win_id.unwrap_or_else(|| Api::get_foreground_window().get_id());
 
-                        let ws_id = workspace.id;
+                if sticky && !state.win_is_managed(win_id) {
+                    manage_window(
+                        state,
+                        rt,
+                        Window::new(win_id),
+                        None,
+                        Some(RuleAction::Float),
+                    );
+                }
+
+                state.with_dsp_containing_win_mut(win_id, |d| {
+                    let config = state.get_display_config(&d.id);
 
-                        d.wm.manage(rt, &state.config.read(), Some(ws_id), area, win)
-                            .unwrap();
+                    if sticky {
+                        let area = d.get_render_area(&config);
+                        if let Err(e) = d.wm.make_sticky(rt, &config, area, win_id) {
+                            error!("Failed to make the window sticky: {:?}", e);
+                        }
+                    } else {
+                        d.wm.unstick(&config, win_id);
                     }
                 });
             }
             WindowAction::Unmanage(maybe_id) => state.with_focused_dsp_mut(|d| {
                 let workspace = d.wm.get_focused_workspace();
-                let area = d.get_render_area(&state.config.read());
+                let config = state.get_display_config(&d.id);
+                let area = d.get_render_area(&config);
 
                 let win = maybe_id
                     .map(Window::new)
                     .or_else(|| workspace.get_focused_win());
 
                 if let Some(win) = win {
-                    if workspace.has_window(win.get_id()) {
+                    if workspace.has_window(win.get_id()) || d.wm.is_sticky(win.get_id()) {
                         info!("'{}' unmanaged", win.get_title());
 
-                        d.wm.unmanage(rt, &state.config.read(), area, win.get_id())
-                            .unwrap();
+                        d.wm.unmanage(rt, &config, area, win.get_id()).unwrap();
                     }
                 }
             }),
         }
     }
 }
+
+/// Manages the window according to the action of the matching rule. A workspace provided by the
+/// caller takes precedence over the workspace of the rule.
+pub fn manage_window(
+    state: &State,
+    rt: &LuaRuntime,
+    win: Window,
+    ws_id: Option<WorkspaceId>,
+    rule_action: Option<RuleAction>,
+) {
+    let ws_id = ws_id.or_else(|| match rule_action {
+        Some(RuleAction::Workspace(id)) => Some(id),
+        _ => None,
+    });
+
+    // The workspace might already exist on another display
+    let dsp_id = ws_id
+        .and_then(|id| state.with_dsp_containing_ws_mut(id, |d| d.id.clone()))
+        .unwrap_or_else(|| state.get_focused_dsp_id());
+
+    let free_ws_id = state.get_free_ws_id();
+
+    // Workspaces of other displays can't be used when the window overflows
+    let taken: Vec<WorkspaceId> = state
+        .displays
+        .read()
+        .iter()
+        .filter(|d| d.id != dsp_id)
+        .flat_map(|d| d.wm.workspaces.iter().map(|ws| ws.id))
+        .collect();
+
+    let overflow = state.with_dsp_mut(dsp_id, |d| {
+        let config = state.get_display_config(&d.id);
+
+        if !win.exists() || d.wm.has_window(win.get_id()) {
+            return None;
+        }
+
+        // Routing a window to a workspace doesn't change the workspace the user is looking at
+        if let Some(ws_id) = ws_id {
+            d.wm.ensure_workspace(&rt, &config, ws_id);
+        } else if d.wm.focused_workspace_id.is_none() {
+            d.wm.change_workspace(&rt, &config, free_ws_id);
+        }
+
+        // Floating windows don't count towards the maximum amount of windows
+        let overflow = match rule_action {
+            Some(RuleAction::Float) => None,
+            _ => {
+                let target_id = ws_id.or(d.wm.focused_workspace_id).unwrap();
+                d.wm.get_overflow_ws_id(&config, target_id, &taken)
+                    .map(|overflow_id| (target_id, overflow_id))
+            }
+        };
+
+        let ws_id = match overflow {
+            Some((_, overflow_id)) => {
+                d.wm.ensure_workspace(&rt, &config, overflow_id);
+                Some(overflow_id)
+            }
+            None => ws_id,
+        };
+
+        let area = d.get_render_area(&config);
+
+        info!("'{}' managed", win.get_title());
+
+        match rule_action {
+            Some(RuleAction::Float) => d.wm.float(&config, ws_id, win),
+            Some(RuleAction::LayoutSlot(slot)) => {
+                d.wm.manage(rt, &config, ws_id, area, win, Some(slot))
+                    .unwrap();
+            }
+            _ => d.wm.manage(rt, &config, ws_id, area, win, None).unwrap(),
+        }
+
+        overflow
+    });
+
+    if let Some((from_ws_id, to_ws_id)) = overflow.flatten() {
+        info!(
+            "'{}' overflowed from Workspace({}) to Workspace({})",
+            win.get_title(),
+            from_ws_id.0,
+            to_ws_id.0
+        );
+
+        if let Err(e) = lua::emit_win_overflow(
+            rt,
+            LuaEvent::WinOverflow {
+                win_id: win.get_id(),
+                from_ws_id,
+                to_ws_id,
+            },
+        ) {
+            error!("{}", lua_error_to_string(e));
+        }
+    }
+}
@@ -1,11 +1,17 @@
 use std::fmt::Display;
 
+use log::error;
+
 use crate::{
+    config::WorkspaceDefinition,
     direction::Direction,
+    display::{DisplayId, DisplayTarget},
     event::Event,
+    layout_preset::{self, LayoutPreset},
     lua::LuaRuntime,
-    platform::NativeWindow,
+    platform::{Api, NativeApi, NativeWindow, Window},
     state::State,
+    template::{self, PendingSlot},
     workspace::{Workspace, WorkspaceId, WorkspaceState},
 };
 
@@ -14,10 +20,32 @@ use super::{Action, WindowAction};
 #[derive(Debug, Clone)]
 pub enum WorkspaceAction {
     Change(WorkspaceId),
+    /// Changes to the previously focused workspace of the focused display
+    BackAndForth,
+    HistoryBack,
+    HistoryForward,
     SetFullscreen(Option<WorkspaceId>, bool),
     SetName(Option<WorkspaceId>, String),
     Focus(Option<WorkspaceId>, Direction),
     Swap(Option<WorkspaceId>, Direction),
+    /// Focuses the next window in depth-first order. When the value is true, the walk continues
+    /// in the next workspace of the display instead of wrapping around.
+    FocusNext(Option<WorkspaceId>, bool),
+    /// Same as `FocusNext`, but in reverse order
+    FocusPrev(Option<WorkspaceId>, bool),
+    /// Focuses the oldest urgent window of the first workspace that has one
+    FocusUrgent,
+    /// Applies the definition of the workspace, creating it if it is persistent
+    ApplyDefinition(WorkspaceId),
+    /// Moves the workspace by the offset in the workspace order, only counting existing
+    /// workspaces
+    Reorder(Option<WorkspaceId>, isize),
+    /// Swaps the positions of the workspaces in the workspace order
+    SwapWorkspaces(WorkspaceId, WorkspaceId),
+    /// Reserves a slot in the layout for every program of the template and launches them
+    ApplyTemplate(Option<WorkspaceId>, String),
+    SaveLayoutPreset(Option<WorkspaceId>, String),
+    ApplyLayoutPreset(Option<WorkspaceId>, String),
 }
 
 impl Display for WorkspaceAction {
@@ -27,6 +55,10 @@ impl Display for WorkspaceAction {
             "{}",
             match self {
                 WorkspaceAction::Change(id) => format!("Changing to Workspace({})", id.0),
+                WorkspaceAction::BackAndForth => String::from("Changing to the previous Workspace"),
+                WorkspaceAction::HistoryBack => String::from("Going back in the Workspace history"),
+                WorkspaceAction::HistoryForward =>
+                    String::from("Going forward in the Workspace history"),
                 WorkspaceAction::SetName(id, value) =>
                     format!("The name of Workspace({:?}) is now '{}'", id, value),
                 WorkspaceAction::SetFullscreen(id, value) =>
@@ -39,6 +71,31 @@ impl Display for WorkspaceAction {
                     format!("Focusing {} in Workspace({:?})", direction, id),
                 WorkspaceAction::Swap(id, direction) =>
                     format!("Swapping {} in Workspace({:?})", direction, id),
+                WorkspaceAction::FocusNext(id, across) => format!(
+                    "Focusing the next window in Workspace({:?}) (across workspaces: {})",
+                    id, across
+                ),
+                WorkspaceAction::FocusPrev(id, across) => format!(
+                    "Focusing the previous window in Workspace({:?}) (across workspaces: {})",
+                    id, across
+                ),
+                WorkspaceAction::FocusUrgent => String::from("Focusing the urgent window"),
+                WorkspaceAction::ApplyDefinition(id) =>
+                    format!("Applying the definition of Workspace({})", id.0),
+                WorkspaceAction::Reorder(id, offset) =>
+                    format!("Moving Workspace({:?}) by {} in the order", id, offset),
+                WorkspaceAction::SwapWorkspaces(a, b) => format!(
+                    "Swapping the positions of Workspace({}) and Workspace({})",
+                    a.0, b.0
+                ),
+                WorkspaceAction::ApplyTemplate(id, name) =>
+                    format!("Applying the template '{}' to Workspace({:?})", name, id),
+                WorkspaceAction::SaveLayoutPreset(id, name) =>
+                    format!("Saving the layout of Workspace({:?}) as '{}'", id, name),
+                WorkspaceAction::ApplyLayoutPreset(id, name) => format!(
+                    "Applying the layout preset '{}' to Workspace({:?})",
+                    name, id
+                ),
             }
         )
     }
@@ -62,7 +119,7 @@ impl WorkspaceAction {
             WorkspaceAction::SetName(maybe_id, value) => {
                 let ws_id = maybe_id.unwrap_or_else(|| state.get_focused_ws_id().unwrap());
 
-                state.with_ws_mut(ws_id, move |ws| ws.display_name = value.clone());
+                state.with_ws_mut(ws_id, move |ws| ws.set_name(value.clone()));
             }
             WorkspaceAction::Focus(maybe_id, dir) => state.with_focused_dsp_mut(|d| {
                 let workspace = d.wm.get_focused_workspace_mut();
@@ -80,38 +137,347 @@ impl WorkspaceAction {
                         .unwrap();
                 }
             }),
+            WorkspaceAction::FocusNext(maybe_id, across) => {
+                focus_in_order(state, rt, maybe_id, across, false)
+            }
+            WorkspaceAction::FocusPrev(maybe_id, across) => {
+                focus_in_order(state, rt, maybe_id, across, true)
+            }
+            WorkspaceAction::FocusUrgent => {
+                let urgent = state.get_ws_order().into_iter().find_map(|ws_id| {
+                    state
+                        .with_ws(ws_id, |ws| ws.urgent_windows.first().copied())
+                        .flatten()
+                        .map(|win_id| (ws_id, win_id))
+                });
+
+                if let Some((ws_id, win_id)) = urgent {
+                    let dsp_id = state.with_dsp_containing_ws_mut(ws_id, |d| {
+                        let config = state.get_display_config(&d.id);
+                        if d.wm.focus_window(rt, &config, win_id) {
+                            Window::new(win_id).focus();
+                        }
+                        d.id.clone()
+                    });
+
+                    if let Some(dsp_id) = dsp_id {
+                        state.set_focused_dsp_id(dsp_id);
+                    }
+                }
+            }
             WorkspaceAction::Swap(maybe_id, dir) => state.with_focused_dsp_mut(|d| {
-                let area = d.get_render_area(&state.config.read());
-                d.wm.swap_in_direction(rt, &state.config.read(), area, None, dir)
+                let config = state.get_display_config(&d.id);
+                let area = d.get_render_area(&config);
+                d.wm.swap_in_direction(rt, &config, area, None, dir)
                     .unwrap();
             }),
             WorkspaceAction::Change(id) => {
-                // There are two cases to consider:
-                //  * The new workspace doesn't exist yet
-                //  * The new workspace already exists
-
-                // `res` is an Option<Option<Window>>
-                //
-                // The first option represents whether the workspace already exists
-                // and the second option whether the workspace has a focused window
-                let res = state.with_ws(id, |ws| ws.get_focused_win());
-
-                match res {
-                    Some(maybe_focused_win) => match maybe_focused_win {
-                        Some(win) => {
-                            state.with_dsp_containing_ws_mut(id, |dsp| {
-                                if dsp.wm.focus_window(&rt, win.get_id()) {
-                                    win.focus();
-                                }
-                            });
+                let auto_back_and_forth = state.config.read().workspace_auto_back_and_forth;
+
+                let id = match state
+                    .with_focused_dsp(|d| (d.wm.focused_workspace_id, d.wm.previous_workspace_id))
+                {
+                    (Some(focused_id), Some(previous_id))
+                        if auto_back_and_forth && focused_id == id =>
+                    {
+                        previous_id
+                    }
+                    _ => id,
+                };
+
+                change_workspace(state, rt, id);
+            }
+            WorkspaceAction::BackAndForth => {
+                if let Some(id) = state.with_focused_dsp(|d| d.wm.previous_workspace_id) {
+                    change_workspace(state, rt, id);
+                }
+            }
+            WorkspaceAction::HistoryBack => {
+                if let Some(id) = state.with_focused_dsp_mut(|d| d.wm.ws_history.back()) {
+                    change_workspace(state, rt, id);
+                }
+            }
+            WorkspaceAction::HistoryForward => {
+                if let Some(id) = state.with_focused_dsp_mut(|d| d.wm.ws_history.forward()) {
+                    change_workspace(state, rt, id);
+                }
+            }
+            WorkspaceAction::ApplyDefinition(id) => {
+                let definition = match state.config.read().workspaces.get(&id) {
+                    Some(x) => x.clone(),
+                    None => return,
+                };
+
+                match state.with_dsp_containing_ws_mut(id, |d| d.id.clone()) {
+                    Some(dsp_id) => {
+                        state.with_ws_mut(id, |ws| ws.apply_definition(&definition));
+
+                        let target_id = get_definition_dsp_id(state, &definition);
+
+                        if target_id != dsp_id {
+                            state
+                                .tx
+                                .send(Event::Action(Action::MoveWorkspaceToDisplay(
+                                    Some(id),
+                                    DisplayTarget::Id(target_id),
+                                )))
+                                .unwrap();
                         }
-                        None => {} //unreachable!("It shouldn't be possible that a workspace exists which doesn't have any windows AND is not focused")
-                    },
+                    }
+                    None if definition.persistent => {
+                        let dsp_id = get_definition_dsp_id(state, &definition);
+
+                        state.with_dsp_mut(dsp_id, |d| {
+                            let config = state.get_display_config(&d.id);
+
+                            if d.wm.focused_workspace_id.is_none() {
+                                d.wm.change_workspace(rt, &config, id);
+                            } else {
+                                d.wm.create_workspace(rt, &config, id);
+                            }
+                        });
+                    }
+                    None => {}
+                }
+            }
+            WorkspaceAction::Reorder(maybe_id, offset) => {
+                let ws_id = match maybe_id.or_else(|| state.get_focused_ws_id()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let mut order = state.get_ws_order();
+                let existing = order
+                    .iter()
+                    .copied()
+                    .filter(|id| state.with_ws(*id, |_| ()).is_some())
+                    .collect::<Vec<_>>();
+
+                let idx = match existing.iter().position(|id| *id == ws_id) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let target_idx =
+                    (idx as isize + offset).clamp(0, existing.len() as isize - 1) as usize;
+
+                if target_idx == idx {
+                    return;
+                }
+
+                let target_id = existing[target_idx];
+
+                order.retain(|id| *id != ws_id);
+
+                let target_pos = order.iter().position(|id| *id == target_id).unwrap();
+
+                if offset > 0 {
+                    order.insert(target_pos + 1, ws_id);
+                } else {
+                    order.insert(target_pos, ws_id);
+                }
+
+                state.set_ws_order(order);
+            }
+            WorkspaceAction::SwapWorkspaces(a, b) => {
+                let mut order = state.get_ws_order();
+
+                for id in [a, b].iter() {
+                    if !order.contains(id) {
+                        order.push(*id);
+                    }
+                }
+
+                let a_pos = order.iter().position(|id| *id == a).unwrap();
+                let b_pos = order.iter().position(|id| *id == b).unwrap();
+
+                order.swap(a_pos, b_pos);
+
+                state.set_ws_order(order);
+            }
+            WorkspaceAction::SaveLayoutPreset(maybe_id, name) => {
+                let ws_id = match maybe_id.or_else(|| state.get_focused_ws_id()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                if let Some(preset) = state.with_ws(ws_id, |ws| LayoutPreset::from_graph(&ws.graph))
+                {
+                    if let Err(e) = layout_preset::save_layout_preset(&name, &preset) {
+                        error!("Failed to save the layout preset '{}': {}", name, e);
+                    }
+                }
+            }
+            WorkspaceAction::ApplyLayoutPreset(maybe_id, name) => {
+                let ws_id = match maybe_id.or_else(|| state.get_focused_ws_id()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                let preset = match layout_preset::load_layout_preset(&name) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error!("{}", e);
+                        return;
+                    }
+                };
+
+                state.with_dsp_containing_ws_mut(ws_id, |d| {
+                    let config = state.get_display_config(&d.id);
+                    let area = d.get_render_area(&config);
+                    if let Err(e) = d.wm.apply_layout_preset(rt, &config, area, ws_id, &preset) {
+                        error!("Failed to apply the layout preset '{}': {:?}", name, e);
+                    }
+                });
+            }
+            WorkspaceAction::ApplyTemplate(maybe_id, name) => {
+                let template = match state.templates.read().get(&name) {
+                    Some(x) => x.clone(),
                     None => {
-                        state.with_focused_dsp_mut(|dsp| dsp.wm.change_workspace(&rt, id));
+                        error!("There is no workspace template called '{}'", name);
+                        return;
                     }
                 };
+
+                let ws_id = match maybe_id.or_else(|| state.get_focused_ws_id()) {
+                    Some(x) => x,
+                    None => return,
+                };
+
+                change_workspace(state, rt, ws_id);
+
+                if let Some(layout) = &template.layout {
+                    state.with_dsp_containing_ws_mut(ws_id, |d| {
+                        if d.wm.get_ws_by_id(ws_id).map_or(false, |ws| ws.is_empty()) {
+                            if let Err(e) = d.wm.set_layout(rt, ws_id, layout) {
+                                error!("Failed to set the layout '{}': {:?}", layout, e);
+                            }
+                        }
+                    });
+                }
+
+                for slot in template.slots {
+                    let placeholder = template::create_placeholder();
+
+                    let added = state.with_dsp_containing_ws_mut(ws_id, |d| {
+                        let config = state.get_display_config(&d.id);
+                        let area = d.get_render_area(&config);
+                        d.wm.add_placeholder(rt, &config, ws_id, area, placeholder)
+                    });
+
+                    if let Some(Err(e)) = added {
+                        error!("Failed to reserve a slot for '{}': {:?}", slot.cmd, e);
+                        continue;
+                    }
+
+                    match Api::launch(&slot.cmd, &slot.launch_options()) {
+                        Ok(pid) => state.pending_slots.write().push(PendingSlot::new(
+                            ws_id,
+                            placeholder,
+                            pid,
+                            slot.matcher.clone(),
+                            Some(slot.launch_command()),
+                        )),
+                        Err(e) => {
+                            error!("Failed to launch '{}': {}", slot.cmd, e);
+
+                            state.with_dsp_containing_ws_mut(ws_id, |d| {
+                                let config = state.get_display_config(&d.id);
+                                let area = d.get_render_area(&config);
+                                if let Err(e) =
+                                    d.wm.remove_placeholder(rt, &config, ws_id, area, placeholder)
+                                {
+                                    error!("Failed to remove the slot of '{}': {:?}", slot.cmd, e);
+                                }
+                            });
+                        }
+                    }
+                }
             }
         }
     }
 }
+
+fn focus_in_order(
+    state: &State,
+    rt: &LuaRuntime,
+    maybe_id: Option<WorkspaceId>,
+    across: bool,
+    reverse: bool,
+) {
+    let ws_id = match maybe_id.or_else(|| state.get_focused_ws_id()) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let win_id = state
+        .with_dsp_containing_ws_mut(ws_id, |d| {
+            let config = state.get_display_config(&d.id);
+            let win_id = d.wm.get_window_in_order(ws_id, across, reverse)?;
+
+            d.wm.focus_window(rt, &config, win_id);
+
+            Some(win_id)
+        })
+        .flatten();
+
+    if let Some(win_id) = win_id {
+        state
+            .tx
+            .send(Event::Action(Action::Window(WindowAction::Focus(win_id))))
+            .unwrap();
+    }
+}
+
+fn change_workspace(state: &State, rt: &LuaRuntime, id: WorkspaceId) {
+    // There are two cases to consider:
+    //  * The new workspace doesn't exist yet
+    //  * The new workspace already exists
+
+    // `res` is an Option<Option<Window>>
+    //
+    // The first option represents whether the workspace already exists
+    // and the second option whether the workspace has a focused window
+    let res = state.with_ws(id, |ws| ws.get_focused_win());
+
+    match res {
+        Some(Some(win)) => {
+            state.with_dsp_containing_ws_mut(id, |dsp| {
+                let config = state.get_display_config(&dsp.id);
+                if dsp.wm.focus_window(&rt, &config, win.get_id()) {
+                    win.focus();
+                }
+            });
+        }
+        // The workspace is either persistent or only contains floating windows
+        Some(None) => {
+            state.with_dsp_containing_ws_mut(id, |dsp| {
+                let config = state.get_display_config(&dsp.id);
+                dsp.wm.change_workspace(&rt, &config, id)
+            });
+        }
+        None => {
+            let dsp_id = match state.config.read().workspaces.get(&id) {
+                Some(definition) => get_definition_dsp_id(state, definition),
+                None => state.get_focused_dsp_id(),
+            };
+
+            state.with_dsp_mut(dsp_id.clone(), |dsp| {
+                let config = state.get_display_config(&dsp.id);
+                dsp.wm.change_workspace(&rt, &config, id)
+            });
+
+            state.set_focused_dsp_id(dsp_id);
+        }
+    };
+}
+
+/// Returns the display the workspace is assigned to, or the focused display in case it isn't
+/// assigned to a connected display.
+fn get_definition_dsp_id(state: &State, definition: &WorkspaceDefinition) -> DisplayId {
+    definition
+        .display
+        .clone()
+        .filter(|id| state.with_dsp(id.clone(), |_| ()).is_some())
+        .unwrap_or_else(|| state.get_focused_dsp_id())
+}
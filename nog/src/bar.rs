@@ -3,13 +3,20 @@ use std::{
     process::{Child, Command},
 };
 
-use crate::paths::get_bin_path;
+use crate::{paths::get_bin_path, platform::Area};
 
-fn create_command() -> Command {
+fn create_command(area: Area) -> Command {
     let mut path = get_bin_path();
     path.push("nog-bar.exe");
 
-    Command::new(path)
+    let mut cmd = Command::new(path);
+
+    cmd.args(["-x", &area.pos.x.to_string()])
+        .args(["-y", &area.pos.y.to_string()])
+        .args(["-W", &area.size.width.to_string()])
+        .args(["-H", &area.size.height.to_string()]);
+
+    cmd
 }
 
 #[derive(Debug)]
@@ -18,9 +25,10 @@ pub struct Bar {
 }
 
 impl Bar {
-    pub fn new() -> io::Result<Self> {
+    /// `area` is the area on the screen the bar occupies
+    pub fn new(area: Area) -> io::Result<Self> {
         Ok(Self {
-            process: create_command().spawn()?,
+            process: create_command(area).spawn()?,
         })
     }
 
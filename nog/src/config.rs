@@ -1,5 +1,184 @@
+use std::{collections::HashMap, ops::Deref, sync::Arc};
+
 use rgb::Rgb;
 
+use crate::{display::DisplayId, platform::NativeWindow, strut::Struts, workspace::WorkspaceId};
+
+/// Overrides of the global config that only apply to a single display. A value of `None` means
+/// that the global value gets used.
+#[derive(Debug, Clone, Default)]
+pub struct DisplayConfig {
+    pub outer_gap: Option<u32>,
+    pub inner_gap: Option<u32>,
+    pub bar_height: Option<u32>,
+    pub display_app_bar: Option<bool>,
+    pub default_layout: Option<String>,
+}
+
+/// Settings of a workspace that got declared by the user
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceDefinition {
+    pub name: Option<String>,
+    pub layout: Option<String>,
+    /// A persistent workspace exists even when it is empty
+    pub persistent: bool,
+    /// The display the workspace gets created on
+    pub display: Option<DisplayId>,
+    /// The maximum amount of tiled windows, which overrides the maximum of the layout
+    pub max_windows: Option<usize>,
+}
+
+/// How the windows of a workspace get hidden when changing to another workspace
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WorkspaceSwitchStrategy {
+    Minimize,
+    Hide,
+    /// Moves the windows outside of every display
+    Offscreen,
+}
+
+impl std::fmt::Display for WorkspaceSwitchStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use WorkspaceSwitchStrategy::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Minimize => "minimize",
+                Hide => "hide",
+                Offscreen => "offscreen",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for WorkspaceSwitchStrategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use WorkspaceSwitchStrategy::*;
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "minimize" => Minimize,
+            "hide" => Hide,
+            "offscreen" => Offscreen,
+            strategy => return Err(format!("Unknown workspace switch strategy '{}'", strategy)),
+        })
+    }
+}
+
+/// What happens to a managed window that gets minimized by the user
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MinimizeBehaviour {
+    Unmanage,
+    /// The window gets removed from the layout and put back into its old slot when it gets
+    /// restored.
+    Park,
+}
+
+impl std::fmt::Display for MinimizeBehaviour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use MinimizeBehaviour::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Unmanage => "unmanage",
+                Park => "park",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for MinimizeBehaviour {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use MinimizeBehaviour::*;
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "unmanage" => Unmanage,
+            "park" => Park,
+            behaviour => return Err(format!("Unknown minimize behaviour '{}'", behaviour)),
+        })
+    }
+}
+
+/// What happens when a window on a workspace that isn't focused activates itself
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FocusStealingPolicy {
+    /// The workspace of the window gets focused
+    Allow,
+    /// The focus goes back to the window that had it before
+    Deny,
+    /// Same as `Deny`, but the window gets marked as urgent
+    Urgent,
+}
+
+impl std::fmt::Display for FocusStealingPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use FocusStealingPolicy::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Allow => "allow",
+                Deny => "deny",
+                Urgent => "urgent",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for FocusStealingPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use FocusStealingPolicy::*;
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "allow" => Allow,
+            "deny" => Deny,
+            "urgent" => Urgent,
+            policy => return Err(format!("Unknown focus stealing policy '{}'", policy)),
+        })
+    }
+}
+
+/// How workspaces without a user defined name get named
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WorkspaceNaming {
+    /// The id of the workspace
+    Manual,
+    /// The application of the most recently focused window
+    Focused,
+    /// The application with the most windows
+    Majority,
+}
+
+impl std::fmt::Display for WorkspaceNaming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use WorkspaceNaming::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Manual => "manual",
+                Focused => "focused",
+                Majority => "majority",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for WorkspaceNaming {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use WorkspaceNaming::*;
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "manual" => Manual,
+            "focused" => Focused,
+            "majority" => Majority,
+            naming => return Err(format!("Unknown workspace naming '{}'", naming)),
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct Config {
     pub color: Rgb,
     pub bar_height: u32,
@@ -18,6 +197,35 @@ pub struct Config {
     ///     * focus
     pub ignore_fullscreen_actions: bool,
     pub display_app_bar: bool,
+    /// When enabled changing to the focused workspace changes to the previous workspace instead
+    pub workspace_auto_back_and_forth: bool,
+    pub workspace_switch_strategy: WorkspaceSwitchStrategy,
+    pub minimize_behaviour: MinimizeBehaviour,
+    /// Processes (e.g. terminals) whose window gets replaced by the windows of programs they
+    /// launch, which are compared case insensitively
+    pub swallow_parents: Vec<String>,
+    pub focus_stealing: FocusStealingPolicy,
+    /// The maximum amount of tiled windows a workspace with the layout can have. Additional
+    /// windows overflow to the next workspace.
+    pub layout_max_windows: HashMap<String, usize>,
+    /// The background color of urgent workspaces in the bar
+    pub urgent_color: Rgb,
+    pub workspace_naming: WorkspaceNaming,
+    /// The names (or icons) of applications, which are used by the automatic workspace naming.
+    /// The keys are either process names, which are compared case insensitively, or window
+    /// classes.
+    pub app_names: HashMap<String, String>,
+    /// The layout a new workspace starts with
+    pub default_layout: String,
+    pub displays: HashMap<DisplayId, DisplayConfig>,
+    /// How the displays get split into virtual displays (see `display::split_displays`)
+    pub display_splits: HashMap<DisplayId, Vec<f32>>,
+    /// Space that is reserved by the user on each display (e.g. for a third-party dock)
+    pub reserved_areas: HashMap<DisplayId, Struts>,
+    pub workspaces: HashMap<WorkspaceId, WorkspaceDefinition>,
+    /// The user defined order of the workspaces. Workspaces that are not part of it come
+    /// afterwards and are ordered by their id.
+    pub workspace_order: Vec<WorkspaceId>,
     // not needed because the user can just use Alt if he wants both and either LAlt or RAlt if he
     // only wants one.
     // pub allow_alt_right: bool
@@ -31,6 +239,85 @@ impl Config {
             Rgb::WHITE
         }
     }
+
+    pub fn is_swallow_parent(&self, process_name: &str) -> bool {
+        self.swallow_parents
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(process_name))
+    }
+
+    /// Returns the maximum amount of tiled windows the workspace can have
+    pub fn get_max_windows(&self, id: WorkspaceId, layout_name: &str) -> Option<usize> {
+        self.workspaces
+            .get(&id)
+            .and_then(|definition| definition.max_windows)
+            .or_else(|| self.layout_max_windows.get(layout_name).copied())
+    }
+
+    /// Returns the name of the application the window belongs to. Without a user defined name
+    /// this is the name of the executable without its extension.
+    pub fn get_app_name(&self, win: &impl NativeWindow) -> String {
+        let process_name = win.get_process_name();
+
+        self.app_names
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&process_name))
+            .map(|(_, name)| name.clone())
+            .or_else(|| self.app_names.get(&win.get_class_name()).cloned())
+            .unwrap_or_else(|| match process_name.rfind('.') {
+                Some(idx) if process_name[idx..].eq_ignore_ascii_case(".exe") => {
+                    process_name[..idx].to_string()
+                }
+                _ => process_name,
+            })
+    }
+
+    pub fn get_ws_order_key(&self, id: WorkspaceId) -> (usize, usize) {
+        let position = self
+            .workspace_order
+            .iter()
+            .position(|x| *x == id)
+            .unwrap_or(usize::MAX);
+
+        (position, id.0)
+    }
+}
+
+/// The config as it applies to a display. Only the values a display can override get copied,
+/// everything else is shared with the global config.
+#[derive(Debug, Clone)]
+pub struct DisplayConfigView {
+    config: Arc<Config>,
+    pub outer_gap: u32,
+    pub inner_gap: u32,
+    pub bar_height: u32,
+    pub display_app_bar: bool,
+    pub default_layout: String,
+}
+
+impl DisplayConfigView {
+    pub fn new(config: Arc<Config>, id: &DisplayId) -> Self {
+        let overrides = config.displays.get(id).cloned().unwrap_or_default();
+
+        Self {
+            outer_gap: overrides.outer_gap.unwrap_or(config.outer_gap),
+            inner_gap: overrides.inner_gap.unwrap_or(config.inner_gap),
+            bar_height: overrides.bar_height.unwrap_or(config.bar_height),
+            display_app_bar: overrides.display_app_bar.unwrap_or(config.display_app_bar),
+            default_layout: overrides
+                .default_layout
+                .unwrap_or_else(|| config.default_layout.clone()),
+            config,
+        }
+    }
+}
+
+impl Deref for DisplayConfigView {
+    type Target = Config;
+
+    fn deref(&self) -> &Self::Target {
+        &self.config
+    }
 }
 
 impl Default for Config {
@@ -48,6 +335,21 @@ impl Default for Config {
             remove_task_bar: true,
             ignore_fullscreen_actions: false,
             display_app_bar: true,
+            workspace_auto_back_and_forth: false,
+            workspace_switch_strategy: WorkspaceSwitchStrategy::Minimize,
+            minimize_behaviour: MinimizeBehaviour::Park,
+            swallow_parents: Vec::new(),
+            focus_stealing: FocusStealingPolicy::Allow,
+            layout_max_windows: HashMap::new(),
+            urgent_color: Rgb::from_hex(0xbf616a),
+            workspace_naming: WorkspaceNaming::Manual,
+            app_names: HashMap::new(),
+            default_layout: "master_slave".into(),
+            displays: HashMap::new(),
+            display_splits: HashMap::new(),
+            reserved_areas: HashMap::new(),
+            workspaces: HashMap::new(),
+            workspace_order: Vec::new(),
         }
     }
 }
@@ -66,6 +368,20 @@ pub enum ConfigProperty {
     RemoveTaskBar(bool),
     IgnoreFullscreenActions(bool),
     DisplayAppBar(bool),
+    WorkspaceAutoBackAndForth(bool),
+    WorkspaceSwitchStrategy(WorkspaceSwitchStrategy),
+    MinimizeBehaviour(MinimizeBehaviour),
+    SwallowParents(Vec<String>),
+    FocusStealing(FocusStealingPolicy),
+    LayoutMaxWindows(HashMap<String, usize>),
+    UrgentColor(Rgb),
+    WorkspaceNaming(WorkspaceNaming),
+    AppNames(HashMap<String, String>),
+    DefaultLayout(String),
+    /// The overrides of the display changed
+    Display(DisplayId),
+    /// The definition of the workspace changed
+    Workspace(WorkspaceId),
 }
 
 impl ConfigProperty {
@@ -83,6 +399,18 @@ impl ConfigProperty {
             ConfigProperty::RemoveTaskBar(_) => "remove_task_bar",
             ConfigProperty::IgnoreFullscreenActions(_) => "ignore_fullscreen_actions",
             ConfigProperty::DisplayAppBar(_) => "display_app_bar",
+            ConfigProperty::WorkspaceAutoBackAndForth(_) => "workspace_auto_back_and_forth",
+            ConfigProperty::WorkspaceSwitchStrategy(_) => "workspace_switch_strategy",
+            ConfigProperty::MinimizeBehaviour(_) => "minimize_behaviour",
+            ConfigProperty::SwallowParents(_) => "swallow_parents",
+            ConfigProperty::FocusStealing(_) => "focus_stealing",
+            ConfigProperty::LayoutMaxWindows(_) => "layout_max_windows",
+            ConfigProperty::UrgentColor(_) => "urgent_color",
+            ConfigProperty::WorkspaceNaming(_) => "workspace_naming",
+            ConfigProperty::AppNames(_) => "app_names",
+            ConfigProperty::DefaultLayout(_) => "default_layout",
+            ConfigProperty::Display(_) => "displays",
+            ConfigProperty::Workspace(_) => "workspaces",
         }
     }
 }
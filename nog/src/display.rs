@@ -1,11 +1,38 @@
+use std::{collections::HashMap, mem};
+
 use crate::bar::Bar;
-use crate::config::Config;
-use crate::platform::{Area, Monitor, NativeMonitor, NativeWindow, Window};
+use crate::config::{Config, DisplayConfigView};
+use crate::direction::Direction;
+use crate::platform::{
+    Area, Monitor, MonitorId, NativeMonitor, NativeWindow, Position, Size, Window,
+};
+use crate::strut::Struts;
 use crate::window_manager::WindowManager;
+use crate::workspace::WorkspaceId;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DisplayId(pub String);
 
+/// Describes which display an action should target
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisplayTarget {
+    Id(DisplayId),
+    /// The closest display in the direction, starting from the display of the affected
+    /// window/workspace
+    Direction(Direction),
+}
+
+/// The part of a monitor that is occupied by a virtual display
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayRegion {
+    /// The id of the display that got split into virtual displays
+    pub parent_id: DisplayId,
+    /// Fraction of the monitor width where the region starts
+    pub start: f32,
+    /// Fraction of the monitor width the region occupies
+    pub width: f32,
+}
+
 #[derive(Debug)]
 pub struct Display {
     pub id: DisplayId,
@@ -13,6 +40,8 @@ pub struct Display {
     pub bar: Option<Bar>,
     pub wm: WindowManager,
     pub monitor: Monitor,
+    /// `None` means that the display occupies the whole monitor
+    pub region: Option<DisplayRegion>,
 }
 
 impl Display {
@@ -24,22 +53,429 @@ impl Display {
         self.taskbar_win.hide();
     }
 
-    /// The area on the display where the windows should get rendered to
-    pub fn get_render_area(&self, config: &Config) -> Area {
-        let mut area = self.monitor.get_work_area();
+    /// The id of the physical display, which is only different from `id` for virtual displays
+    pub fn get_physical_id(&self) -> DisplayId {
+        self.region
+            .as_ref()
+            .map(|region| region.parent_id.clone())
+            .unwrap_or_else(|| self.id.clone())
+    }
 
-        if config.display_app_bar {
-            area.pos.y += config.bar_height as isize;
-            area.size.height -= config.bar_height as usize;
+    /// The work area of the monitor limited to the region of the display
+    pub fn get_work_area(&self) -> Area {
+        self.limit_to_region(self.monitor.get_work_area())
+    }
+
+    fn limit_to_region(&self, area: Area) -> Area {
+        match &self.region {
+            Some(region) => {
+                let width = area.size.width as f32;
+                // Calculating the end instead of the width avoids gaps between the regions caused
+                // by rounding.
+                let start = (width * region.start) as usize;
+                let end = (width * (region.start + region.width)) as usize;
+
+                Area::new(
+                    Size::new(end - start, area.size.height),
+                    Position::new(area.pos.x + start as isize, area.pos.y),
+                )
+            }
+            None => area,
+        }
+    }
+
+    /// The space the taskbar occupies on the monitor. The taskbar always spans one of the edges,
+    /// which is why the longer side decides whether it is horizontal or vertical.
+    fn get_taskbar_struts(&self, monitor_area: Area) -> Struts {
+        let pos = self.taskbar_win.get_position();
+        let size = self.taskbar_win.get_size();
+        let mut struts = Struts::default();
+
+        if size.width >= size.height {
+            if pos.y <= monitor_area.pos.y {
+                struts.top = size.height;
+            } else {
+                struts.bottom = size.height;
+            }
+        } else if pos.x <= monitor_area.pos.x {
+            struts.left = size.width;
+        } else {
+            struts.right = size.width;
         }
 
+        struts
+    }
+
+    /// The space that is reserved by the system (e.g. the taskbar or other appbars). When the
+    /// taskbar gets removed its space is available again.
+    fn get_system_struts(&self, config: &Config) -> Struts {
+        let monitor_area = self.monitor.get_area();
+        let struts = Struts::between(monitor_area, self.monitor.get_work_area());
+
         if config.remove_task_bar {
-            // TODO: revisit this.
-            //
-            // This is only temporary, we should fetch the taskbar size instead of hardcoding it.
-            area.size.height += 40;
+            struts.without(self.get_taskbar_struts(monitor_area))
+        } else {
+            struts
+        }
+    }
+
+    /// The area of the display that isn't reserved by the system or the user
+    fn get_available_area(&self, config: &Config) -> Area {
+        let area = self
+            .get_system_struts(config)
+            .apply(self.monitor.get_area());
+
+        config
+            .reserved_areas
+            .get(&self.id)
+            .copied()
+            .unwrap_or_default()
+            .apply(self.limit_to_region(area))
+    }
+
+    /// The area on the display where the windows should get rendered to
+    pub fn get_render_area(&self, config: &DisplayConfigView) -> Area {
+        let mut struts = Struts::default();
+
+        if config.display_app_bar {
+            struts.top = config.bar_height as usize;
         }
 
+        struts.apply(self.get_available_area(config))
+    }
+
+    /// The area on the display where the bar should get rendered to
+    pub fn get_bar_area(&self, config: &DisplayConfigView) -> Area {
+        let mut area = self.get_available_area(config);
+
+        area.size.height = config.bar_height as usize;
+
         area
     }
 }
+
+/// Returns the index of the area that is the closest to `from` in the given direction.
+///
+/// An area is only considered to be in a direction if its center lies past the center of `from`
+/// on the corresponding axis. The distance on the other axis is used to break ties.
+pub fn find_area_in_direction(from: Area, areas: &[Area], dir: Direction) -> Option<usize> {
+    let origin = from.get_center();
+
+    areas
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, area)| {
+            let center = area.get_center();
+            let dx = center.x - origin.x;
+            let dy = center.y - origin.y;
+
+            let (distance, offset) = match dir {
+                Direction::Left => (-dx, dy.abs()),
+                Direction::Right => (dx, dy.abs()),
+                Direction::Up => (-dy, dx.abs()),
+                Direction::Down => (dy, dx.abs()),
+            };
+
+            if distance > 0 {
+                Some((idx, distance, offset))
+            } else {
+                None
+            }
+        })
+        .min_by_key(|(_, distance, offset)| (*distance, *offset))
+        .map(|(idx, _, _)| idx)
+}
+
+/// Describes what changed when reconciling the displays
+#[derive(Debug, Default)]
+pub struct DisplayChanges {
+    pub added: Vec<DisplayId>,
+    pub removed: Vec<DisplayId>,
+    /// See `DisplayPlan::targets`
+    pub targets: Vec<(DisplayId, DisplayId)>,
+    /// The workspaces that got moved from a removed display and the display they got moved to
+    pub migrated: Vec<(WorkspaceId, DisplayId)>,
+}
+
+impl DisplayChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The parts of a display that decide what happens to its workspaces when the displays change
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayInfo {
+    pub id: DisplayId,
+    pub monitor_id: MonitorId,
+    pub primary: bool,
+}
+
+impl DisplayInfo {
+    pub fn from_display(display: &Display) -> Self {
+        Self {
+            id: display.id.clone(),
+            monitor_id: display.monitor.id,
+            primary: display.monitor.primary,
+        }
+    }
+}
+
+/// Describes how to get from the old displays to the new ones
+#[derive(Debug, Default, PartialEq)]
+pub struct DisplayPlan {
+    pub added: Vec<DisplayId>,
+    pub removed: Vec<DisplayId>,
+    /// Every removed display together with the display that takes over its workspaces
+    pub targets: Vec<(DisplayId, DisplayId)>,
+}
+
+/// Decides which displays got added and removed, and where the workspaces of a removed display
+/// go. A display on the same monitor is preferred, otherwise the primary display (or the first
+/// one if there is no primary display) takes over.
+///
+/// If `new` is empty nothing changes, because we would lose every workspace otherwise.
+pub fn plan_display_changes(old: &[DisplayInfo], new: &[DisplayInfo]) -> DisplayPlan {
+    let mut plan = DisplayPlan::default();
+
+    if new.is_empty() {
+        return plan;
+    }
+
+    plan.added = new
+        .iter()
+        .filter(|n| !old.iter().any(|o| o.id == n.id))
+        .map(|n| n.id.clone())
+        .collect();
+
+    for o in old.iter().filter(|o| !new.iter().any(|n| n.id == o.id)) {
+        // A display on the same monitor is the most likely replacement, which is always the case
+        // when splitting a display into virtual displays or when reverting the split.
+        let target = new
+            .iter()
+            .find(|n| n.monitor_id == o.monitor_id)
+            .or_else(|| new.iter().find(|n| n.primary))
+            .unwrap_or(&new[0]);
+
+        plan.removed.push(o.id.clone());
+        plan.targets.push((o.id.clone(), target.id.clone()));
+    }
+
+    plan
+}
+
+/// Replaces `displays` with `new_displays` while keeping the window manager and bar of every
+/// display that still exists (see `plan_display_changes`).
+///
+/// This function doesn't minimize or render anything, the caller is responsible for doing that
+/// based on the returned changes.
+pub fn reconcile_displays(
+    displays: &mut Vec<Display>,
+    mut new_displays: Vec<Display>,
+) -> DisplayChanges {
+    let plan = plan_display_changes(
+        &displays
+            .iter()
+            .map(DisplayInfo::from_display)
+            .collect::<Vec<_>>(),
+        &new_displays
+            .iter()
+            .map(DisplayInfo::from_display)
+            .collect::<Vec<_>>(),
+    );
+
+    let mut changes = DisplayChanges {
+        added: plan.added,
+        removed: plan.removed,
+        targets: plan.targets,
+        migrated: Vec::new(),
+    };
+
+    if new_displays.is_empty() {
+        return changes;
+    }
+
+    let mut orphans = Vec::new();
+
+    for mut old in mem::take(displays) {
+        match new_displays.iter_mut().find(|new| new.id == old.id) {
+            // The monitor and taskbar handles can change whenever the display configuration
+            // changes, so we only carry over our own state.
+            Some(new) => {
+                new.wm = mem::replace(&mut old.wm, WindowManager::new());
+                new.bar = old.bar.take();
+            }
+            None => orphans.push(old),
+        }
+    }
+
+    for mut orphan in orphans {
+        if let Some(mut bar) = orphan.bar.take() {
+            bar.close();
+        }
+
+        let target_id = changes
+            .targets
+            .iter()
+            .find(|(id, _)| *id == orphan.id)
+            .map(|(_, target_id)| target_id)
+            .expect("Every removed display has a target");
+        let target = new_displays
+            .iter_mut()
+            .find(|d| d.id == *target_id)
+            .unwrap();
+
+        for ws in mem::take(&mut orphan.wm.workspaces) {
+            changes.migrated.push((ws.id, target.id.clone()));
+            target.wm.workspaces.push(ws);
+        }
+
+        target
+            .wm
+            .sticky_windows
+            .extend(mem::take(&mut orphan.wm.sticky_windows));
+
+        target
+            .wm
+            .window_cleanup
+            .extend(mem::take(&mut orphan.wm.window_cleanup));
+
+        if target.wm.focused_workspace_id.is_none() {
+            target.wm.focused_workspace_id = orphan
+                .wm
+                .focused_workspace_id
+                .or_else(|| target.wm.workspaces.first().map(|ws| ws.id));
+        }
+    }
+
+    *displays = new_displays;
+
+    changes
+}
+
+/// Replaces every display that has a split configured with one virtual display per part. Each
+/// part describes how much of the monitor width the virtual display occupies relative to the
+/// other parts.
+pub fn split_displays(
+    displays: Vec<Display>,
+    splits: &HashMap<DisplayId, Vec<f32>>,
+) -> Vec<Display> {
+    let mut result = Vec::new();
+
+    for display in displays {
+        let parts = match splits.get(&display.id) {
+            Some(parts) if parts.len() > 1 => parts,
+            _ => {
+                result.push(display);
+                continue;
+            }
+        };
+
+        let total = parts.iter().sum::<f32>();
+        let mut start = 0.0;
+
+        for (idx, part) in parts.iter().enumerate() {
+            let width = part / total;
+
+            result.push(Display {
+                id: DisplayId(format!("{}#{}", display.id.0, idx + 1)),
+                taskbar_win: display.taskbar_win,
+                bar: None,
+                wm: WindowManager::new(),
+                monitor: display.monitor.clone(),
+                region: Some(DisplayRegion {
+                    parent_id: display.id.clone(),
+                    start,
+                    width,
+                }),
+            });
+
+            start += width;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: &str, monitor_id: isize, primary: bool) -> DisplayInfo {
+        DisplayInfo {
+            id: DisplayId(id.to_string()),
+            monitor_id: MonitorId(monitor_id),
+            primary,
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<DisplayId> {
+        ids.iter().map(|id| DisplayId(id.to_string())).collect()
+    }
+
+    #[test]
+    fn unplug_moves_workspaces_to_primary() {
+        let old = [info("a", 1, true), info("b", 2, false)];
+        let new = [info("a", 1, true)];
+
+        let plan = plan_display_changes(&old, &new);
+
+        assert_eq!(plan.added, ids(&[]));
+        assert_eq!(plan.removed, ids(&["b"]));
+        assert_eq!(
+            plan.targets,
+            vec![(DisplayId("b".into()), DisplayId("a".into()))]
+        );
+    }
+
+    #[test]
+    fn replug_only_adds() {
+        let old = [info("a", 1, true)];
+        let new = [info("a", 3, true), info("b", 4, false)];
+
+        let plan = plan_display_changes(&old, &new);
+
+        assert_eq!(plan.added, ids(&["b"]));
+        assert!(plan.removed.is_empty());
+        assert!(plan.targets.is_empty());
+    }
+
+    #[test]
+    fn split_prefers_same_monitor() {
+        let old = [info("a", 1, true), info("b", 2, false)];
+        let new = [
+            info("a", 1, true),
+            info("b#1", 2, false),
+            info("b#2", 2, false),
+        ];
+
+        let plan = plan_display_changes(&old, &new);
+
+        assert_eq!(plan.added, ids(&["b#1", "b#2"]));
+        assert_eq!(plan.removed, ids(&["b"]));
+        assert_eq!(
+            plan.targets,
+            vec![(DisplayId("b".into()), DisplayId("b#1".into()))]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_display_without_primary() {
+        let old = [info("a", 1, false), info("b", 2, false)];
+        let new = [info("c", 3, false), info("b", 2, false)];
+
+        let plan = plan_display_changes(&old, &new);
+
+        assert_eq!(
+            plan.targets,
+            vec![(DisplayId("a".into()), DisplayId("c".into()))]
+        );
+    }
+
+    #[test]
+    fn empty_list_changes_nothing() {
+        let old = [info("a", 1, true)];
+
+        assert_eq!(plan_display_changes(&old, &[]), DisplayPlan::default());
+    }
+}
@@ -28,6 +28,10 @@ pub enum Event {
     ShowMenu,
     Exit,
     RenderBarLayout,
+    /// A display got connected or disconnected
+    DisplaysChanged,
+    /// Removes the placeholders of template slots whose window didn't show up in time
+    ExpirePendingSlots,
     Defered(DeferedFunction),
     Window(WindowEvent<Window>),
     Keybinding(KeyCombination),
@@ -284,6 +284,25 @@ impl Graph {
             .collect()
     }
 
+    /// Returns the window nodes in the order they get rendered (depth first)
+    pub fn get_window_nodes_in_order(&self) -> Vec<GraphNodeId> {
+        let mut nodes = Vec::new();
+        self.collect_window_nodes(self.root_node_id, &mut nodes);
+        nodes
+    }
+
+    fn collect_window_nodes(&self, id: GraphNodeId, nodes: &mut Vec<GraphNodeId>) {
+        match self.get_node(id) {
+            Some(GraphNode::Window(_)) => nodes.push(id),
+            Some(GraphNode::Group { .. }) => {
+                for child in self.get_children(id) {
+                    self.collect_window_nodes(child, nodes);
+                }
+            }
+            None => {}
+        }
+    }
+
     // A parent edge is where the child has the given id
     pub fn get_parent_edge(&self, id: GraphNodeId) -> Option<usize> {
         self.edges
@@ -338,6 +357,17 @@ impl Graph {
         self.add_child_node(new_parent, node_cpy).unwrap();
     }
 
+    /// Replaces the window of a window node without changing the structure of the graph, which
+    /// keeps the ids that the layout stored intact.
+    pub fn replace_window(&mut self, old: WindowNodeId, new: WindowNodeId) -> GraphResult {
+        let id = self.get_window_node(old).ok_or(GraphError::NodeNotFound)?;
+
+        self.nodes.insert(id, GraphNode::Window(new));
+        self.dirty = true;
+
+        Ok(())
+    }
+
     pub fn swap_nodes(&mut self, x: GraphNodeId, y: GraphNodeId) {
         for edge in &mut self.edges {
             if edge.child == x {
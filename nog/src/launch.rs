@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{platform::NativeWindow, workspace::WorkspaceId};
+
+/// How long nog waits for the first window of a launched program before it forgets about the
+/// launch. Process ids get reused, so waiting forever could route unrelated windows.
+pub const LAUNCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    /// Variables that get added to the environment nog was started with
+    pub env: HashMap<String, String>,
+    /// The workspace the first window of the program gets managed in
+    pub workspace: Option<WorkspaceId>,
+    /// Whether the first window of the program gets floated, which overrides the rules
+    pub float: Option<bool>,
+}
+
+/// How nog launched a program, so that a session can launch it again
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchCommand {
+    pub cmd: String,
+    pub args: Vec<String>,
+}
+
+/// A launched program whose first window didn't show up yet
+#[derive(Debug, Clone)]
+pub struct PendingLaunch {
+    pub pid: u32,
+    pub command: LaunchCommand,
+    pub workspace: Option<WorkspaceId>,
+    pub float: Option<bool>,
+    launched_at: Instant,
+}
+
+impl PendingLaunch {
+    pub fn new(pid: u32, cmd: &str, options: &LaunchOptions) -> Self {
+        Self {
+            pid,
+            command: LaunchCommand {
+                cmd: cmd.to_string(),
+                args: options.args.clone(),
+            },
+            workspace: options.workspace,
+            float: options.float,
+            launched_at: Instant::now(),
+        }
+    }
+}
+
+/// Removes and returns the launch that created the window. Programs often start their window
+/// from a child process (e.g. launchers), which is why the parents of the process are checked
+/// too.
+pub fn take_pending_launch(
+    launches: &mut Vec<PendingLaunch>,
+    win: &impl NativeWindow,
+) -> Option<PendingLaunch> {
+    launches.retain(|launch| launch.launched_at.elapsed() < LAUNCH_TIMEOUT);
+
+    if launches.is_empty() {
+        return None;
+    }
+
+    let pid = win.get_process_id();
+    let idx = match launches.iter().position(|launch| launch.pid == pid) {
+        Some(idx) => idx,
+        None => {
+            let parent_ids = win.get_parent_process_ids();
+            launches
+                .iter()
+                .position(|launch| parent_ids.contains(&launch.pid))?
+        }
+    };
+
+    Some(launches.remove(idx))
+}
@@ -0,0 +1,266 @@
+//! A layout preset stores the shape of a graph without any windows, so that it can be applied to
+//! any workspace.
+//!
+//! Presets are saved in the `layout_presets` directory next to the sessions. Each line of a
+//! preset file describes a node, where the indentation (two spaces per level) denotes the parent
+//! of the node. The first line is the root node. A group can't directly contain a group of the
+//! same kind (e.g. a row in a row), since the graph couldn't tell them apart.
+//!
+//! Example preset:
+//!
+//! ```text
+//! row
+//!   slot
+//!   col
+//!     slot
+//!     slot
+//! ```
+
+use crate::graph::{Graph, GraphNode, GraphNodeGroupKind, GraphNodeId};
+use crate::paths::get_config_path;
+use crate::platform::WindowId;
+use std::{
+    fs, io,
+    iter::Peekable,
+    path::{Component, Path, PathBuf},
+};
+
+const INDENT: &str = "  ";
+
+#[derive(Debug, Clone)]
+pub enum PresetNode {
+    Group(GraphNodeGroupKind, Vec<PresetNode>),
+    /// Gets replaced by a window when the preset is applied
+    Slot,
+}
+
+impl PresetNode {
+    pub fn has_slots(&self) -> bool {
+        match self {
+            PresetNode::Slot => true,
+            PresetNode::Group(_, children) => children.iter().any(|child| child.has_slots()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LayoutPreset {
+    pub root: PresetNode,
+}
+
+impl LayoutPreset {
+    pub fn from_graph(graph: &Graph) -> Self {
+        Self {
+            root: preset_node_from_graph(graph, graph.root_node_id),
+        }
+    }
+
+    /// Builds a graph with the shape of the preset, where the slots get filled in depth-first
+    /// order. The windows that don't fit into the preset are left in the iterator and groups
+    /// that would end up without any windows are left out.
+    pub fn build_graph(&self, windows: &mut Peekable<impl Iterator<Item = WindowId>>) -> Graph {
+        let mut graph = Graph::new();
+        let root_id = graph.root_node_id;
+
+        if let PresetNode::Group(kind, children) = &self.root {
+            graph.nodes.insert(
+                root_id,
+                GraphNode::Group {
+                    kind: *kind,
+                    focus: 0,
+                    child_count: 0,
+                },
+            );
+
+            for child in children {
+                add_preset_node(&mut graph, root_id, child, windows);
+            }
+        }
+
+        graph
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                let trimmed = line.trim_start_matches(INDENT);
+                let depth = (line.len() - trimmed.len()) / INDENT.len();
+                (idx + 1, depth, trimmed.trim())
+            })
+            .peekable();
+
+        let root = parse_preset_node(&mut lines, 0, None)?;
+
+        if let Some((line_nr, _, _)) = lines.next() {
+            return Err(format!("Line {}: Only one root node is allowed", line_nr));
+        }
+
+        match root {
+            PresetNode::Slot => Err(String::from("The root node has to be a row or a column")),
+            root => Ok(Self { root }),
+        }
+    }
+}
+
+impl std::fmt::Display for LayoutPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_preset_node(f, &self.root, 0)
+    }
+}
+
+fn preset_node_from_graph(graph: &Graph, id: GraphNodeId) -> PresetNode {
+    match graph.get_node(id) {
+        Some(GraphNode::Group { kind, .. }) => {
+            let mut children = Vec::new();
+
+            for child in graph.get_children(id) {
+                match preset_node_from_graph(graph, child) {
+                    // A group of the same kind is flattened into its parent, which renders the
+                    // same way and keeps the preset valid
+                    PresetNode::Group(child_kind, grandchildren) if child_kind == *kind => {
+                        children.extend(grandchildren)
+                    }
+                    node => children.push(node),
+                }
+            }
+
+            PresetNode::Group(*kind, children)
+        }
+        _ => PresetNode::Slot,
+    }
+}
+
+fn add_preset_node(
+    graph: &mut Graph,
+    parent: GraphNodeId,
+    node: &PresetNode,
+    windows: &mut Peekable<impl Iterator<Item = WindowId>>,
+) {
+    if windows.peek().is_none() || !node.has_slots() {
+        return;
+    }
+
+    match node {
+        PresetNode::Slot => {
+            let win_id = windows.next().unwrap();
+            graph.add_window(parent, win_id).unwrap();
+        }
+        PresetNode::Group(kind, children) => {
+            let id = match kind {
+                GraphNodeGroupKind::Row => graph.add_row(parent),
+                GraphNodeGroupKind::Col => graph.add_col(parent),
+            }
+            .unwrap();
+
+            for child in children {
+                add_preset_node(graph, id, child, windows);
+            }
+        }
+    }
+}
+
+fn parse_preset_node<'a>(
+    lines: &mut Peekable<impl Iterator<Item = (usize, usize, &'a str)>>,
+    depth: usize,
+    parent_kind: Option<GraphNodeGroupKind>,
+) -> Result<PresetNode, String> {
+    let (line_nr, line_depth, value) = lines
+        .next()
+        .ok_or_else(|| String::from("The preset is empty"))?;
+
+    if line_depth != depth {
+        return Err(format!("Line {}: Unexpected indentation", line_nr));
+    }
+
+    let kind = match value {
+        "slot" => return Ok(PresetNode::Slot),
+        "row" => GraphNodeGroupKind::Row,
+        "col" => GraphNodeGroupKind::Col,
+        value => return Err(format!("Line {}: Unknown node '{}'", line_nr, value)),
+    };
+
+    if parent_kind == Some(kind) {
+        return Err(format!(
+            "Line {}: A {} can't be the direct child of a {}",
+            line_nr, value, value
+        ));
+    }
+
+    let mut children = Vec::new();
+
+    while let Some((_, child_depth, _)) = lines.peek() {
+        if *child_depth <= depth {
+            break;
+        }
+
+        children.push(parse_preset_node(lines, depth + 1, Some(kind))?);
+    }
+
+    Ok(PresetNode::Group(kind, children))
+}
+
+fn write_preset_node(
+    f: &mut std::fmt::Formatter<'_>,
+    node: &PresetNode,
+    depth: usize,
+) -> std::fmt::Result {
+    let indent = INDENT.repeat(depth);
+
+    match node {
+        PresetNode::Slot => writeln!(f, "{}slot", indent),
+        PresetNode::Group(kind, children) => {
+            writeln!(
+                f,
+                "{}{}",
+                indent,
+                match kind {
+                    GraphNodeGroupKind::Row => "row",
+                    GraphNodeGroupKind::Col => "col",
+                }
+            )?;
+
+            for child in children {
+                write_preset_node(f, child, depth + 1)?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// The name has to be a plain file name, so that presets can't be read from or written to
+/// other directories (e.g. `../config.lua`).
+fn get_preset_path(name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => {}
+        _ => return Err(format!("'{}' is not a valid layout preset name", name)),
+    }
+
+    let mut path = get_config_path();
+    path.push("layout_presets");
+    path.push(name);
+    Ok(path)
+}
+
+pub fn save_layout_preset(name: &str, preset: &LayoutPreset) -> io::Result<()> {
+    let path = get_preset_path(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, preset.to_string())
+}
+
+pub fn load_layout_preset(name: &str) -> Result<LayoutPreset, String> {
+    let content = fs::read_to_string(get_preset_path(name)?)
+        .map_err(|e| format!("Failed to read the layout preset '{}': {}", name, e))?;
+
+    LayoutPreset::parse(&content)
+        .map_err(|e| format!("The layout preset '{}' is invalid: {}", name, e))
+}
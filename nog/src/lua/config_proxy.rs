@@ -1,20 +1,27 @@
 use crate::{
     action::{Action, UpdateConfigActionFn},
-    config::{Config, ConfigProperty},
+    config::{
+        Config, ConfigProperty, FocusStealingPolicy, MinimizeBehaviour, WorkspaceNaming,
+        WorkspaceSwitchStrategy,
+    },
     event::Event,
     thread_safe::ThreadSafe,
 };
 use mlua::prelude::*;
 use rgb::Rgb;
-use std::{mem, sync::mpsc::SyncSender};
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{mpsc::SyncSender, Arc},
+};
 
 pub struct ConfigProxy {
-    config: ThreadSafe<Config>,
+    config: ThreadSafe<Arc<Config>>,
     tx: SyncSender<Event>,
 }
 
 impl ConfigProxy {
-    pub fn new(tx: SyncSender<Event>, config: ThreadSafe<Config>) -> Self {
+    pub fn new(tx: SyncSender<Event>, config: ThreadSafe<Arc<Config>>) -> Self {
         Self { config, tx }
     }
 }
@@ -45,7 +52,17 @@ impl mlua::UserData for ConfigProxy {
                 remove_decorations,
                 remove_task_bar,
                 ignore_fullscreen_actions,
-                display_app_bar
+                display_app_bar,
+                workspace_auto_back_and_forth,
+                workspace_switch_strategy,
+                minimize_behaviour,
+                swallow_parents,
+                focus_stealing,
+                layout_max_windows,
+                urgent_color,
+                workspace_naming,
+                app_names,
+                default_layout
             };
 
             Ok(value)
@@ -59,7 +76,8 @@ impl mlua::UserData for ConfigProxy {
                         match key.as_str() {
                             $(stringify!($name) => {
                                 let value = <$ty>::from_lua(value, lua)?;
-                                let old_value = mem::replace(&mut this.config.write().$name, value);
+                                let mut config = this.config.write();
+                                let old_value = mem::replace(&mut Arc::make_mut(&mut config).$name, value);
                                 Some(ConfigProperty::$enum(old_value))
                             }),*
                             _ => None,
@@ -79,7 +97,17 @@ impl mlua::UserData for ConfigProxy {
                     remove_decorations: bool => RemoveDecorations,
                     remove_task_bar: bool => RemoveTaskBar,
                     ignore_fullscreen_actions: bool => IgnoreFullscreenActions,
-                    display_app_bar: bool => DisplayAppBar
+                    display_app_bar: bool => DisplayAppBar,
+                    workspace_auto_back_and_forth: bool => WorkspaceAutoBackAndForth,
+                    workspace_switch_strategy: WorkspaceSwitchStrategy => WorkspaceSwitchStrategy,
+                    minimize_behaviour: MinimizeBehaviour => MinimizeBehaviour,
+                    swallow_parents: Vec<String> => SwallowParents,
+                    focus_stealing: FocusStealingPolicy => FocusStealing,
+                    layout_max_windows: HashMap<String, usize> => LayoutMaxWindows,
+                    urgent_color: Rgb => UrgentColor,
+                    workspace_naming: WorkspaceNaming => WorkspaceNaming,
+                    app_names: HashMap<String, String> => AppNames,
+                    default_layout: String => DefaultLayout
                 };
 
                 if let Some(prop) = config_prop {
@@ -1,11 +1,21 @@
+use crate::config::{
+    DisplayConfig, FocusStealingPolicy, MinimizeBehaviour, WorkspaceDefinition, WorkspaceNaming,
+    WorkspaceSwitchStrategy,
+};
 use crate::direction::Direction;
-use crate::display::DisplayId;
+use crate::display::{DisplayId, DisplayTarget};
 use crate::key_combination::KeyCombination;
 use crate::keybinding::KeybindingMode;
+use crate::launch::LaunchOptions;
 use crate::platform::{MonitorId, Size, WindowId};
+use crate::rule::{LayoutSlot, Rule, RuleAction, WindowMatcher};
+use crate::strut::Edge;
+use crate::template::{TemplateSlot, WorkspaceTemplate};
 use crate::workspace::WorkspaceId;
 use mlua::prelude::*;
+use regex::Regex;
 use rgb::Rgb;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use super::LuaEvent;
@@ -79,6 +89,202 @@ impl<'lua> FromLua<'lua> for DisplayId {
     }
 }
 
+impl<'lua> FromLua<'lua> for DisplayTarget {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match String::from_lua(lua_value.clone(), lua) {
+            Ok(string) => Ok(match Direction::from_str(&string) {
+                Ok(dir) => DisplayTarget::Direction(dir),
+                Err(_) => DisplayTarget::Id(DisplayId(string)),
+            }),
+            Err(_) => Err(LuaError::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "DisplayTarget",
+                message: Some("Expected either a display id or a direction".into()),
+            }),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for DisplayConfig {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        Ok(DisplayConfig {
+            outer_gap: tbl.get("outer_gap")?,
+            inner_gap: tbl.get("inner_gap")?,
+            bar_height: tbl.get("bar_height")?,
+            display_app_bar: tbl.get("display_app_bar")?,
+            default_layout: tbl.get("default_layout")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for WorkspaceDefinition {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        Ok(WorkspaceDefinition {
+            name: tbl.get("name")?,
+            layout: tbl.get("layout")?,
+            persistent: tbl
+                .get::<_, Option<bool>>("persistent")?
+                .unwrap_or_default(),
+            display: tbl.get("display")?,
+            max_windows: tbl.get("max_windows")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for LaunchOptions {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        Ok(LaunchOptions {
+            args: tbl
+                .get::<_, Option<Vec<String>>>("args")?
+                .unwrap_or_default(),
+            cwd: tbl.get("cwd")?,
+            env: tbl
+                .get::<_, Option<HashMap<String, String>>>("env")?
+                .unwrap_or_default(),
+            workspace: tbl.get("workspace")?,
+            float: tbl.get("float")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for TemplateSlot {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        Ok(TemplateSlot {
+            cmd: tbl.get("cmd")?,
+            args: tbl
+                .get::<_, Option<Vec<String>>>("args")?
+                .unwrap_or_default(),
+            cwd: tbl.get("cwd")?,
+            matcher: tbl.get("match")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for WorkspaceTemplate {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        Ok(WorkspaceTemplate {
+            layout: tbl.get("layout")?,
+            slots: tbl
+                .get::<_, Option<Vec<TemplateSlot>>>("slots")?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for RuleAction {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match lua_value {
+            LuaValue::String(s) => match s.to_str()? {
+                "ignore" => Ok(RuleAction::Ignore),
+                "float" => Ok(RuleAction::Float),
+                action => Err(LuaError::RuntimeError(format!(
+                    "Unknown rule action '{}'",
+                    action
+                ))),
+            },
+            LuaValue::Table(tbl) => {
+                if let Some(ws_id) = tbl.get("workspace")? {
+                    Ok(RuleAction::Workspace(ws_id))
+                } else if let Some(slot) = tbl.get("layout_slot")? {
+                    Ok(RuleAction::LayoutSlot(slot))
+                } else {
+                    Err(LuaError::RuntimeError(
+                        "Expected a rule action table with either workspace or layout_slot".into(),
+                    ))
+                }
+            }
+            value => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "RuleAction",
+                message: Some("Expected either a string or a table".into()),
+            }),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for LayoutSlot {
+    fn from_lua(lua_value: LuaValue<'lua>, _lua: &'lua Lua) -> LuaResult<Self> {
+        let position = match lua_value {
+            LuaValue::String(s) => return Ok(LayoutSlot::Named(s.to_str()?.to_string())),
+            LuaValue::Integer(x) => x as f64,
+            LuaValue::Number(x) => x,
+            value => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "LayoutSlot",
+                    message: Some("Expected either a string or a number".into()),
+                })
+            }
+        };
+
+        if position < 1.0 || position.fract() != 0.0 {
+            return Err(LuaError::FromLuaConversionError {
+                from: "number",
+                to: "LayoutSlot",
+                message: Some("Expected a whole number starting at 1".into()),
+            });
+        }
+
+        Ok(LayoutSlot::Position(position as usize))
+    }
+}
+
+impl<'lua> ToLua<'lua> for LayoutSlot {
+    fn to_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        match self {
+            LayoutSlot::Named(name) => name.to_lua(lua),
+            LayoutSlot::Position(position) => position.to_lua(lua),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for WindowMatcher {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        let title_pattern = tbl
+            .get::<_, Option<String>>("title_pattern")?
+            .map(|pattern| Regex::new(&pattern))
+            .transpose()
+            .map_err(|e| LuaError::RuntimeError(e.to_string()))?;
+
+        let matcher = WindowMatcher {
+            class: tbl.get("class")?,
+            title_pattern,
+            process: tbl.get("process")?,
+        };
+
+        if matcher.is_empty() {
+            return Err(LuaError::RuntimeError(
+                "A matcher needs at least one of class, title_pattern or process".into(),
+            ));
+        }
+
+        Ok(matcher)
+    }
+}
+
+impl<'lua> FromLua<'lua> for Rule {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        let tbl = mlua::Table::from_lua(lua_value, lua)?;
+
+        Ok(Rule {
+            matcher: WindowMatcher::from_lua(LuaValue::Table(tbl.clone()), lua)?,
+            action: tbl.get("action")?,
+        })
+    }
+}
+
 impl<'lua> ToLua<'lua> for WorkspaceId {
     fn to_lua(self, _lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
         Ok(mlua::Value::Number(self.0 as f64))
@@ -131,6 +337,138 @@ impl<'lua> FromLua<'lua> for Direction {
     }
 }
 
+impl<'lua> FromLua<'lua> for Edge {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match String::from_lua(lua_value.clone(), lua) {
+            Ok(string) => match Edge::from_str(&string) {
+                Ok(x) => Ok(x),
+                Err(msg) => Err(LuaError::FromLuaConversionError {
+                    from: lua_value.type_name(),
+                    to: "Edge",
+                    message: Some(msg),
+                }),
+            },
+            Err(_) => Err(LuaError::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "Edge",
+                message: Some("Expected a type that can be coerced into a string".into()),
+            }),
+        }
+    }
+}
+
+impl<'lua> ToLua<'lua> for FocusStealingPolicy {
+    fn to_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let s = lua.create_string(&self.to_string())?;
+
+        s.to_lua(lua)
+    }
+}
+
+impl<'lua> FromLua<'lua> for FocusStealingPolicy {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match String::from_lua(lua_value.clone(), lua) {
+            Ok(string) => match FocusStealingPolicy::from_str(&string) {
+                Ok(x) => Ok(x),
+                Err(msg) => Err(LuaError::FromLuaConversionError {
+                    from: lua_value.type_name(),
+                    to: "FocusStealingPolicy",
+                    message: Some(msg),
+                }),
+            },
+            Err(_) => Err(LuaError::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "FocusStealingPolicy",
+                message: Some("Expected a type that can be coerced into a string".into()),
+            }),
+        }
+    }
+}
+
+impl<'lua> ToLua<'lua> for WorkspaceNaming {
+    fn to_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let s = lua.create_string(&self.to_string())?;
+
+        s.to_lua(lua)
+    }
+}
+
+impl<'lua> FromLua<'lua> for WorkspaceNaming {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match String::from_lua(lua_value.clone(), lua) {
+            Ok(string) => match WorkspaceNaming::from_str(&string) {
+                Ok(x) => Ok(x),
+                Err(msg) => Err(LuaError::FromLuaConversionError {
+                    from: lua_value.type_name(),
+                    to: "WorkspaceNaming",
+                    message: Some(msg),
+                }),
+            },
+            Err(_) => Err(LuaError::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "WorkspaceNaming",
+                message: Some("Expected a type that can be coerced into a string".into()),
+            }),
+        }
+    }
+}
+
+impl<'lua> ToLua<'lua> for WorkspaceSwitchStrategy {
+    fn to_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let s = lua.create_string(&self.to_string())?;
+
+        s.to_lua(lua)
+    }
+}
+
+impl<'lua> FromLua<'lua> for WorkspaceSwitchStrategy {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match String::from_lua(lua_value.clone(), lua) {
+            Ok(string) => match WorkspaceSwitchStrategy::from_str(&string) {
+                Ok(x) => Ok(x),
+                Err(msg) => Err(LuaError::FromLuaConversionError {
+                    from: lua_value.type_name(),
+                    to: "WorkspaceSwitchStrategy",
+                    message: Some(msg),
+                }),
+            },
+            Err(_) => Err(LuaError::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "WorkspaceSwitchStrategy",
+                message: Some("Expected a type that can be coerced into a string".into()),
+            }),
+        }
+    }
+}
+
+impl<'lua> ToLua<'lua> for MinimizeBehaviour {
+    fn to_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
+        let s = lua.create_string(&self.to_string())?;
+
+        s.to_lua(lua)
+    }
+}
+
+impl<'lua> FromLua<'lua> for MinimizeBehaviour {
+    fn from_lua(lua_value: LuaValue<'lua>, lua: &'lua Lua) -> LuaResult<Self> {
+        match String::from_lua(lua_value.clone(), lua) {
+            Ok(string) => match MinimizeBehaviour::from_str(&string) {
+                Ok(x) => Ok(x),
+                Err(msg) => Err(LuaError::FromLuaConversionError {
+                    from: lua_value.type_name(),
+                    to: "MinimizeBehaviour",
+                    message: Some(msg),
+                }),
+            },
+            Err(_) => Err(LuaError::FromLuaConversionError {
+                from: lua_value.type_name(),
+                to: "MinimizeBehaviour",
+                message: Some("Expected a type that can be coerced into a string".into()),
+            }),
+        }
+    }
+}
+
 impl<'lua> ToLua<'lua> for Size {
     fn to_lua(self, lua: &'lua Lua) -> LuaResult<LuaValue<'lua>> {
         let tbl = lua.create_table()?;
@@ -161,6 +499,34 @@ impl<'lua> ToLua<'lua> for LuaEvent {
                 tbl.raw_set("ws_id", ws_id)?;
                 mlua::Value::Table(tbl)
             }
+            LuaEvent::DisplayChanged { added, removed } => {
+                let tbl = lua.create_table()?;
+                tbl.raw_set("added", added)?;
+                tbl.raw_set("removed", removed)?;
+                mlua::Value::Table(tbl)
+            }
+            LuaEvent::WinOverflow {
+                win_id,
+                from_ws_id,
+                to_ws_id,
+            } => {
+                let tbl = lua.create_table()?;
+                tbl.raw_set("win_id", win_id)?;
+                tbl.raw_set("from_ws_id", from_ws_id)?;
+                tbl.raw_set("to_ws_id", to_ws_id)?;
+                mlua::Value::Table(tbl)
+            }
+            LuaEvent::SlotExpired {
+                ws_id,
+                session,
+                window,
+            } => {
+                let tbl = lua.create_table()?;
+                tbl.raw_set("ws_id", ws_id)?;
+                tbl.raw_set("session", session)?;
+                tbl.raw_set("window", window)?;
+                mlua::Value::Table(tbl)
+            }
         })
     }
 }
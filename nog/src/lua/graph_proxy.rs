@@ -57,6 +57,14 @@ impl<'a> mlua::UserData for GraphProxy<'a> {
             },
         );
 
+        methods.add_method(
+            "get_children",
+            |_lua, this, parent_id: Option<GraphNodeId>| {
+                let parent_id = parent_id.unwrap_or(this.0.root_node_id);
+                Ok(this.0.get_children(parent_id))
+            },
+        );
+
         methods.add_method(
             "get_window_node_in_direction",
             |_lua, this, (start, direction): (GraphNodeId, Direction)| {
@@ -4,7 +4,7 @@ pub mod graph_proxy;
 pub mod namespace;
 pub mod runtime;
 
-use std::{os::windows::process::CommandExt, process::Command};
+use std::{os::windows::process::CommandExt, process::Command, sync::Arc};
 
 pub use namespace::LuaNamespace;
 pub use runtime::LuaRuntime;
@@ -13,18 +13,23 @@ use mlua::prelude::*;
 
 use crate::{
     action::{Action, WindowAction, WorkspaceAction},
+    config::{ConfigProperty, DisplayConfig, WorkspaceDefinition},
     constants::get_version,
     direction::Direction,
-    display::DisplayId,
+    display::{DisplayId, DisplayTarget},
     event::Event,
     file_watcher::FileWatcher,
     key_combination::KeyCombination,
     keybinding::KeybindingMode,
+    launch::{LaunchOptions, PendingLaunch},
     lua::config_proxy::ConfigProxy,
     notification::Notification,
     paths::{get_config_path, get_runtime_path},
     platform::{Api, NativeApi, NativeWindow, Window, WindowId},
+    rule::{Rule, RuleAction},
     state::State,
+    strut::Edge,
+    template::WorkspaceTemplate,
     workspace::{WorkspaceId, WorkspaceState},
 };
 use rgb::Rgb;
@@ -105,6 +110,25 @@ pub enum LuaEvent {
     WsCreated {
         ws_id: WorkspaceId,
     },
+    DisplayChanged {
+        added: Vec<DisplayId>,
+        removed: Vec<DisplayId>,
+    },
+    /// The window got managed in another workspace, because the workspace it was meant for
+    /// already has the maximum amount of tiled windows
+    WinOverflow {
+        win_id: WindowId,
+        from_ws_id: WorkspaceId,
+        to_ws_id: WorkspaceId,
+    },
+    /// The window of a template or session slot didn't show up in time, which is why its
+    /// placeholder got removed
+    SlotExpired {
+        ws_id: WorkspaceId,
+        /// `None` for the slots of templates
+        session: Option<String>,
+        window: String,
+    },
 }
 
 pub fn init_events(rt: &LuaRuntime) -> LuaResult<()> {
@@ -114,6 +138,15 @@ pub fn init_events(rt: &LuaRuntime) -> LuaResult<()> {
     rt.lua
         .set_named_registry_value("ws_created", rt.lua.create_table()?)?;
 
+    rt.lua
+        .set_named_registry_value("display_changed", rt.lua.create_table()?)?;
+
+    rt.lua
+        .set_named_registry_value("win_overflow", rt.lua.create_table()?)?;
+
+    rt.lua
+        .set_named_registry_value("slot_expired", rt.lua.create_table()?)?;
+
     Ok(())
 }
 
@@ -129,24 +162,111 @@ pub fn get_event_handlers_iter<'a>(
         .flatten())
 }
 
-/// Returns whether any event handler returned false
-pub fn emit_win_manage(rt: &LuaRuntime, event: LuaEvent) -> LuaResult<()> {
+/// Changes to the way a window gets managed, which can be returned by a `win_manage` event
+/// handler (e.g. `{ ws_id = 4, float = true }`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManageRedirect {
+    pub ws_id: Option<WorkspaceId>,
+    pub float: Option<bool>,
+}
+
+impl ManageRedirect {
+    /// Uses the values of `other` where this redirect doesn't define any
+    pub fn or(self, other: ManageRedirect) -> Self {
+        Self {
+            ws_id: self.ws_id.or(other.ws_id),
+            float: self.float.or(other.float),
+        }
+    }
+
+    /// Combines the redirect with the action of the rule that matched the window
+    pub fn apply_to_rule_action(&self, rule_action: Option<RuleAction>) -> Option<RuleAction> {
+        match self.float {
+            Some(true) => Some(RuleAction::Float),
+            Some(false) if rule_action == Some(RuleAction::Float) => None,
+            _ => rule_action,
+        }
+    }
+}
+
+/// Returns `None` if any event handler returned false, otherwise the combined redirects of the
+/// event handlers. Later handlers override the values of earlier ones.
+pub fn emit_win_manage(rt: &LuaRuntime, event: LuaEvent) -> LuaResult<Option<ManageRedirect>> {
+    let mut redirect = ManageRedirect::default();
+
     for ev_handler in get_event_handlers_iter(rt, "win_manage")? {
-        ev_handler.call::<LuaEvent, Option<mlua::Table>>(event.clone())?;
+        match ev_handler.call::<LuaEvent, LuaValue>(event.clone())? {
+            LuaValue::Boolean(false) => return Ok(None),
+            LuaValue::Table(tbl) => {
+                if let Some(ws_id) = tbl.get("ws_id")? {
+                    redirect.ws_id = Some(ws_id);
+                }
+
+                if let Some(float) = tbl.get("float")? {
+                    redirect.float = Some(float);
+                }
+            }
+            _ => {}
+        }
     }
 
-    Ok(())
+    Ok(Some(redirect))
 }
 
-/// Returns whether any event handler returned false
+/// The return values of the event handlers are ignored
 pub fn emit_ws_created(rt: &LuaRuntime, event: LuaEvent) -> LuaResult<()> {
     for ev_handler in get_event_handlers_iter(rt, "ws_created")? {
-        ev_handler.call::<LuaEvent, Option<mlua::Table>>(event.clone())?;
+        ev_handler.call::<LuaEvent, ()>(event.clone())?;
+    }
+
+    Ok(())
+}
+
+pub fn emit_display_changed(rt: &LuaRuntime, event: LuaEvent) -> LuaResult<()> {
+    for ev_handler in get_event_handlers_iter(rt, "display_changed")? {
+        ev_handler.call::<LuaEvent, ()>(event.clone())?;
     }
 
     Ok(())
 }
 
+pub fn emit_win_overflow(rt: &LuaRuntime, event: LuaEvent) -> LuaResult<()> {
+    for ev_handler in get_event_handlers_iter(rt, "win_overflow")? {
+        ev_handler.call::<LuaEvent, ()>(event.clone())?;
+    }
+
+    Ok(())
+}
+
+pub fn emit_slot_expired(rt: &LuaRuntime, event: LuaEvent) -> LuaResult<()> {
+    for ev_handler in get_event_handlers_iter(rt, "slot_expired")? {
+        ev_handler.call::<LuaEvent, ()>(event.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Displays can either be referenced by their id or by their index (starting at 1)
+fn get_dsp_id_by_selector<'lua>(
+    state: &State,
+    lua: &'lua Lua,
+    value: LuaValue<'lua>,
+) -> LuaResult<DisplayId> {
+    match value {
+        idx @ LuaValue::Integer(..) | idx @ LuaValue::Number(..) => {
+            let idx = usize::from_lua(idx, lua)?;
+
+            state
+                .displays
+                .read()
+                .get(idx.wrapping_sub(1))
+                .map(|d| d.id.clone())
+                .ok_or_else(|| LuaError::RuntimeError(format!("Display {} doesn't exist", idx)))
+        }
+        id => DisplayId::from_lua(id, lua),
+    }
+}
+
 pub fn init(state: State) -> LuaResult<LuaRuntime> {
     let rt = LuaRuntime::new(state.clone())?;
 
@@ -321,6 +441,113 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
                 Ok(())
             }
 
+            fn ws_define(ws_id: WorkspaceId, definition: WorkspaceDefinition) {
+                inject state;
+
+                Arc::make_mut(&mut state.config.write())
+                    .workspaces
+                    .insert(ws_id, definition);
+                state.tx.send(Event::Action(Action::UpdateConfig(ConfigProperty::Workspace(ws_id))))
+                    .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_template(name: String, template: WorkspaceTemplate) {
+                inject state;
+
+                state.templates.write().insert(name, template);
+
+                Ok(())
+            }
+
+            fn ws_apply_template(name: String, ws_id: Option<WorkspaceId>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::ApplyTemplate(
+                    ws_id, name,
+                ))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn layout_preset_save(name: String, ws_id: Option<WorkspaceId>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(
+                    WorkspaceAction::SaveLayoutPreset(ws_id, name),
+                )))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn layout_preset_apply(name: String, ws_id: Option<WorkspaceId>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(
+                    WorkspaceAction::ApplyLayoutPreset(ws_id, name),
+                )))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_move_left(ws_id: Option<WorkspaceId>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::Reorder(ws_id, -1))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_move_right(ws_id: Option<WorkspaceId>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::Reorder(ws_id, 1))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_swap_order(a: WorkspaceId, b: WorkspaceId) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::SwapWorkspaces(a, b))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_back_and_forth() {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::BackAndForth)))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_history_back() {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::HistoryBack)))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_history_forward() {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::HistoryForward)))
+                .unwrap();
+
+                Ok(())
+            }
+
             fn move_win_to_ws(win_id: Option<WindowId>, ws_id: WorkspaceId) {
                 inject state;
 
@@ -330,6 +557,24 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
                 Ok(())
             }
 
+            fn move_win_to_dsp(win_id: Option<WindowId>, target: DisplayTarget) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::MoveWindowToDisplay(win_id, target)))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn move_ws_to_dsp(ws_id: Option<WorkspaceId>, target: DisplayTarget) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::MoveWorkspaceToDisplay(ws_id, target)))
+                .unwrap();
+
+                Ok(())
+            }
+
             fn ws_focus(ws_id: Option<WorkspaceId>, direction: Direction) {
                 inject state;
 
@@ -347,6 +592,21 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
                 Ok(state.with_ws(ws_id, |ws| ws.get_focused_win().map(|w| w.get_id())).flatten())
             }
 
+            fn ws_is_urgent(ws_id: WorkspaceId) {
+                inject state;
+
+                Ok(state.with_ws(ws_id, |ws| ws.is_urgent()).unwrap_or(false))
+            }
+
+            fn ws_focus_urgent() {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::FocusUrgent)))
+                    .unwrap();
+
+                Ok(())
+            }
+
             fn ws_is_fullscreen(ws_id: WorkspaceId) {
                 inject state;
 
@@ -383,22 +643,37 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
             fn ws_get_all() {
                 inject state;
 
-                let mut workspaces = vec![];
+                let workspaces = state
+                    .get_ws_order()
+                    .into_iter()
+                    .filter(|id| state.with_ws(*id, |_| ()).is_some())
+                    .collect::<Vec<_>>();
 
-                for d in state.displays.read().iter() {
-                    let ws_ids = d
-                        .wm
-                        .workspaces
-                        .iter()
-                        .map(|w| w.id)
-                        .collect::<Vec<_>>();
+                Ok(workspaces)
+            }
 
-                    for id in ws_ids {
-                        workspaces.push(id);
-                    }
-                }
+            fn ws_focus_next(ws_id: Option<WorkspaceId>, across: Option<bool>) {
+                inject state;
 
-                Ok(workspaces)
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::FocusNext(
+                    ws_id,
+                    across.unwrap_or(false),
+                ))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn ws_focus_prev(ws_id: Option<WorkspaceId>, across: Option<bool>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Workspace(WorkspaceAction::FocusPrev(
+                    ws_id,
+                    across.unwrap_or(false),
+                ))))
+                .unwrap();
+
+                Ok(())
             }
 
             fn ws_swap(ws_id: Option<WorkspaceId>, direction: Direction) {
@@ -420,10 +695,11 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
                 Ok(())
             }
 
-            fn session_load(name: String) {
+            fn session_load(name: String, launch: Option<bool>) {
                 inject state;
 
-                state.tx.send(Event::Action(Action::LoadSession(name))).unwrap();
+                state.tx.send(Event::Action(Action::LoadSession(name, launch.unwrap_or(false))))
+                    .unwrap();
 
                 Ok(())
             }
@@ -454,24 +730,115 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
                 Ok(())
             }
 
+            fn win_focus_last() {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Window(WindowAction::FocusLast)))
+                    .unwrap();
+
+                Ok(())
+            }
+
+            fn win_cycle_mru(reverse: Option<bool>) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Window(WindowAction::CycleMru(
+                    reverse.unwrap_or(false),
+                ))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn win_is_urgent(win_id: Option<WindowId>) {
+                inject state;
+
+                let id = win_id.unwrap_or_else(|| Api::get_foreground_window().get_id());
+
+                Ok(state
+                    .displays
+                    .read()
+                    .iter()
+                    .any(|d| d.wm.workspaces.iter().any(|ws| ws.is_win_urgent(id))))
+            }
+
+            fn win_set_sticky(win_id: Option<WindowId>, sticky: bool) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::Window(WindowAction::SetSticky(
+                    win_id,
+                    sticky,
+                ))))
+                .unwrap();
+
+                Ok(())
+            }
+
+            fn win_is_sticky(win_id: Option<WindowId>) {
+                inject state;
+
+                let id = win_id.unwrap_or_else(|| Api::get_foreground_window().get_id());
+
+                Ok(state.displays.read().iter().any(|d| d.wm.is_sticky(id)))
+            }
+
             fn win_get_title(win_id: WindowId) {
                 inject state;
 
                 Ok(Window::new(win_id).get_title())
             }
 
+            fn win_get_class(win_id: WindowId) {
+                inject state;
+
+                Ok(Window::new(win_id).get_class_name())
+            }
+
+            fn win_get_process(win_id: WindowId) {
+                inject state;
+
+                Ok(Window::new(win_id).get_process_name())
+            }
+
+            fn rule(rule: Rule) {
+                inject state;
+
+                state.rules.write().push(rule);
+
+                Ok(())
+            }
+
             fn win_get_size(win_id: WindowId) {
                 inject state;
 
                 Ok(Window::new(win_id).get_size())
             }
 
-            fn launch(path: String) {
+            fn launch(cmd: String, options: Option<LaunchOptions>) {
                 inject state;
 
-                state.tx.send(Event::Action(Action::Launch(path))).unwrap();
+                let options = options.unwrap_or_default();
 
-                Ok(())
+                // Returns nil if the pid of the program is unknown, which is the case when it had
+                // to be started through the shell (e.g. documents or urls)
+                let pid = Api::launch(&cmd, &options).map_err(|e| {
+                    LuaError::RuntimeError(format!("Failed to launch '{}': {}", cmd, e))
+                })?;
+
+                if let Some(pid) = pid {
+                    state
+                        .pending_launches
+                        .write()
+                        .push(PendingLaunch::new(pid, &cmd, &options));
+                }
+
+                Ok(pid)
+            }
+
+            fn stats_get(days: Option<u32>) {
+                inject lua, state;
+
+                lua.to_value(&state.stats.read().get_stats(days.unwrap_or(1)))
             }
 
             fn win_manage(win_id: Option<WindowId>) {
@@ -502,6 +869,64 @@ pub fn init(state: State) -> LuaResult<LuaRuntime> {
                 Ok(state.with_dsp(dsp_id, |dsp| dsp.wm.workspaces.iter().map(|ws| ws.id).collect::<Vec<_>>()))
             }
 
+            fn dsp_set_config(dsp: mlua::Value, settings: DisplayConfig) {
+                inject lua, state;
+
+                let dsp_id = get_dsp_id_by_selector(state, lua, dsp)?;
+
+                Arc::make_mut(&mut state.config.write())
+                    .displays
+                    .insert(dsp_id.clone(), settings);
+                state.tx.send(Event::Action(Action::UpdateConfig(ConfigProperty::Display(dsp_id))))
+                    .unwrap();
+
+                Ok(())
+            }
+
+            fn dsp_split(dsp: mlua::Value, parts: Vec<f32>) {
+                inject lua, state;
+
+                if parts.iter().any(|part| *part <= 0.0) {
+                    return Err(mlua::Error::RuntimeError(String::from("Every part of a split has to be bigger than 0")));
+                }
+
+                let dsp_id = get_dsp_id_by_selector(state, lua, dsp)?;
+                let physical_id = state
+                    .with_dsp(dsp_id.clone(), |d| d.get_physical_id())
+                    .ok_or_else(|| mlua::Error::RuntimeError(format!("Display {} doesn't exist", dsp_id.0)))?;
+
+                Arc::make_mut(&mut state.config.write())
+                    .display_splits
+                    .insert(physical_id, parts);
+                state.tx.send(Event::DisplaysChanged).unwrap();
+
+                Ok(())
+            }
+
+            fn dsp_reserve(dsp: mlua::Value, edge: Edge, amount: usize) {
+                inject lua, state;
+
+                let dsp_id = get_dsp_id_by_selector(state, lua, dsp)?;
+
+                Arc::make_mut(&mut state.config.write())
+                    .reserved_areas
+                    .entry(dsp_id.clone())
+                    .or_default()
+                    .set(edge, amount);
+                state.tx.send(Event::Action(Action::UpdateConfig(ConfigProperty::Display(dsp_id))))
+                    .unwrap();
+
+                Ok(())
+            }
+
+            fn dsp_focus(direction: Direction) {
+                inject state;
+
+                state.tx.send(Event::Action(Action::FocusDisplay(direction))).unwrap();
+
+                Ok(())
+            }
+
             fn dsp_get_focused() {
                 inject state;
 
@@ -4,7 +4,7 @@ use action::Action;
 use chrono::Duration;
 use event::Event;
 use keybinding_event_loop::KeybindingEventLoop;
-use log::{error, info};
+use log::{error, info, warn};
 use mlua::FromLua;
 use nog_protocol::{BarContent, BarItem, BarItemAlignment};
 use rgb::Rgb;
@@ -18,10 +18,12 @@ use window_event_loop::WindowEventLoop;
 
 use crate::{
     action::WindowAction,
-    lua::{lua_error_to_string, LuaEvent},
+    config::{FocusStealingPolicy, MinimizeBehaviour},
+    lua::{lua_error_to_string, LuaEvent, ManageRedirect},
     notification::{Notification, NotificationManager},
     paths::get_bin_path,
     platform::{Api, NativeApi, NativeWindow},
+    rule::{self, RuleAction},
     state::State,
     system_tray::SystemTray,
     window_event_loop::WindowEventKind,
@@ -53,16 +55,22 @@ mod key;
 mod key_combination;
 mod keybinding;
 mod keybinding_event_loop;
+mod launch;
+mod layout_preset;
 mod logging;
 mod lua;
 mod modifiers;
 mod notification;
 mod paths;
 mod platform;
+mod rule;
 mod server;
 mod session;
 mod state;
+mod stats;
+mod strut;
 mod system_tray;
+mod template;
 mod thread_safe;
 mod window_event_loop;
 mod window_manager;
@@ -150,6 +158,37 @@ fn failable_main() -> Result<(), Error> {
         panic!("Couldn't find any displays the fuck?");
     }
 
+    // Windows only notifies windows about display changes, so we just poll the connected
+    // displays instead.
+    let _display_change_timer = {
+        let timer = timer::Timer::new();
+        let tx = tx.clone();
+        let mut display_ids = Api::get_display_ids();
+        (
+            timer.schedule_repeating(Duration::seconds(2), move || {
+                let new_display_ids = Api::get_display_ids();
+
+                if new_display_ids != display_ids {
+                    display_ids = new_display_ids;
+                    tx.send(Event::DisplaysChanged).unwrap();
+                }
+            }),
+            timer,
+        )
+    };
+
+    // Placeholders of template slots have to disappear even if no other window gets created
+    let _pending_slot_timer = {
+        let timer = timer::Timer::new();
+        let tx = tx.clone();
+        (
+            timer.schedule_repeating(Duration::seconds(2), move || {
+                tx.send(Event::ExpirePendingSlots).unwrap();
+            }),
+            timer,
+        )
+    };
+
     let rt = lua::init(state.clone()).map_err(Error::Lua)?;
 
     // Only really used in development to make sure everything is cleaned up
@@ -179,7 +218,7 @@ fn failable_main() -> Result<(), Error> {
     info!("Keybinding event loop spawned");
 
     let mut notification_manager = NotificationManager::new(
-        &state.with_focused_dsp(|dsp| dsp.get_render_area(&state.config.read())),
+        &state.with_focused_dsp(|dsp| dsp.get_render_area(&state.get_display_config(&dsp.id))),
     );
     info!("Notification Manager initialized");
 
@@ -193,12 +232,51 @@ fn failable_main() -> Result<(), Error> {
                 WindowEventKind::FocusChanged => {
                     if state.is_awake() {
                         let win_id = win_event.window.get_id();
-                        state.with_dsp_containing_win_mut(win_id, |d| {
-                            if d.wm.focus_window(&rt, win_id) {
+                        let policy = state.config.read().focus_stealing;
+                        let focused = state.with_dsp_containing_win_mut(win_id, |d| {
+                            if d.wm.is_in_background(win_id) && policy != FocusStealingPolicy::Allow
+                            {
+                                info!("Window with id {} tried to steal the focus", win_id);
+
+                                if policy == FocusStealingPolicy::Urgent {
+                                    d.wm.mark_urgent(win_id);
+                                }
+
+                                d.wm.push_to_background(win_id);
+
+                                if let Some(win) =
+                                    d.wm.focused_workspace_id
+                                        .and_then(|id| d.wm.get_ws_by_id(id))
+                                        .and_then(|ws| ws.get_focused_win())
+                                {
+                                    win.focus();
+                                }
+
+                                return None;
+                            }
+
+                            let config = state.get_display_config(&d.id);
+                            if d.wm.focus_window(&rt, &config, win_id) {
                                 info!("Focused window with id {}", win_event.window.get_id());
                                 win_event.window.focus();
                             }
+                            Some((d.id.clone(), d.wm.focused_workspace_id))
                         });
+
+                        match focused.flatten() {
+                            Some((dsp_id, ws_id)) => {
+                                if let Some(ws_id) = ws_id {
+                                    state
+                                        .stats
+                                        .write()
+                                        .focus(win_event.window.get_process_name(), ws_id);
+                                }
+
+                                state.set_focused_dsp_id(dsp_id);
+                            }
+                            // The focus doesn't belong to a managed window anymore
+                            None => state.stats.write().stop(),
+                        }
                     }
                 }
                 WindowEventKind::Created => {
@@ -206,7 +284,15 @@ fn failable_main() -> Result<(), Error> {
                         let win = win_event.window;
 
                         if state
-                            .with_dsp_containing_win_mut(win.get_id(), |_| ())
+                            .with_dsp_containing_win_mut(win.get_id(), |d| {
+                                // A parked window that hid itself (e.g. into the system tray)
+                                // shows up again.
+                                let config = state.get_display_config(&d.id);
+                                let area = d.get_render_area(&config);
+                                if let Err(e) = d.wm.unpark(&rt, &config, area, win.get_id()) {
+                                    error!("Failed to unpark '{}': {:?}", win.get_title(), e);
+                                }
+                            })
                             .is_some()
                         {
                             log::debug!("Window is already managed");
@@ -224,52 +310,168 @@ fn failable_main() -> Result<(), Error> {
                             continue;
                         }
 
-                        lua::emit_win_manage(
+                        // The template explicitly asked for the window of a slot, which is why
+                        // neither the rules nor the win_manage handlers get to decide about it.
+                        let pending_slot =
+                            template::take_pending_slot(&mut state.pending_slots.write(), &win);
+
+                        if let Some(slot) = pending_slot {
+                            let filled = state
+                                .with_dsp_containing_ws_mut(slot.ws_id, |d| {
+                                    let config = state.get_display_config(&d.id);
+                                    let area = d.get_render_area(&config);
+                                    d.wm.fill_placeholder(
+                                        &config,
+                                        slot.ws_id,
+                                        area,
+                                        slot.placeholder,
+                                        win,
+                                    )
+                                })
+                                .unwrap_or(false);
+
+                            if filled {
+                                info!("'{}' filled a template slot", title);
+
+                                if let Some(command) = slot.command {
+                                    state.launch_commands.write().insert(win.get_id(), command);
+                                }
+
+                                continue;
+                            }
+                        }
+
+                        let pending_launch =
+                            launch::take_pending_launch(&mut state.pending_launches.write(), &win);
+                        let mut rule_action = rule::find_action(&state.rules.read(), &win);
+
+                        // The user explicitly asked for the window of a launched program, which is
+                        // why an ignore rule doesn't apply to it.
+                        if pending_launch.is_some() && rule_action == Some(RuleAction::Ignore) {
+                            rule_action = None;
+                        }
+
+                        if rule_action == Some(RuleAction::Ignore) {
+                            log::debug!("'{}' is ignored by a rule", title);
+                            continue;
+                        }
+
+                        if let Some(launch) = &pending_launch {
+                            state
+                                .launch_commands
+                                .write()
+                                .insert(win.get_id(), launch.command.clone());
+                        }
+
+                        let launch_redirect = pending_launch
+                            .map(|launch| ManageRedirect {
+                                ws_id: launch.workspace,
+                                float: launch.float,
+                            })
+                            .unwrap_or_default();
+
+                        let redirect = match lua::emit_win_manage(
                             &rt,
                             LuaEvent::WinManage {
                                 manual: false,
-                                ws_id: None,
+                                ws_id: launch_redirect.ws_id,
                                 win_id: win.get_id(),
                             },
-                        )
-                        .unwrap();
+                        ) {
+                            Ok(Some(redirect)) => redirect.or(launch_redirect),
+                            Ok(None) => {
+                                info!("Managing '{}' got cancelled", title);
+                                continue;
+                            }
+                            // A broken handler shouldn't keep the window from getting managed
+                            Err(e) => {
+                                error!("{}", lua_error_to_string(e));
+                                launch_redirect
+                            }
+                        };
 
                         info!("'{}' created", win.get_title());
-                        state.with_focused_dsp_mut(|d| {
-                            let area = d.get_render_area(&state.config.read());
-                            d.wm.manage(&rt, &state.config.read(), None, area, win)
-                                .unwrap();
-                        });
+                        action::manage_window(
+                            &state,
+                            &rt,
+                            win,
+                            redirect.ws_id,
+                            redirect.apply_to_rule_action(rule_action),
+                        );
                     }
                 }
                 WindowEventKind::Deleted => {
+                    let win_id = win_event.window.get_id();
+                    window_event_loop::forget_expected_events(win_id);
+                    state.launch_commands.write().remove(&win_id);
+
                     if state.is_awake() {
-                        let win_id = win_event.window.get_id();
                         state.with_dsp_containing_win_mut(win_id, |d| {
-                            let area = d.get_render_area(&state.config.read());
-                            d.wm.unmanage(&rt, &state.config.read(), area, win_id)
-                                .unwrap();
+                            let config = state.get_display_config(&d.id);
+                            let area = d.get_render_area(&config);
+                            d.wm.unmanage(&rt, &config, area, win_id).unwrap();
                             info!("'{}' deleted", win_event.window.get_title());
                         });
                     }
                 }
                 WindowEventKind::Minimized => {
-                    //TODO: Changing workspaces minimizes the windows of the previous workspace.
-                    //This then causes this event for each window, resulting in unmanaging each
-                    //window. Somehow ignore this event when changing workspaces.
-                    //
-                    //Maybe just not unmanaging a manually minimized window is an option?
-
-                    //
-                    // let win_id = win_event.window.get_id();
-
-                    // state.with_dsp_containing_win_mut(win_id, |d| {
-                    //     info!("Managed window {} got minimzed", win_id);
-                    //     let area = d.get_render_area(&state.config.read());
-                    //     d.wm.unmanage(&rt, &state.config.read(), area, win_id)
-                    //         .unwrap();
-                    //     info!("'{}' minimized", win_event.window.get_title());
-                    // });
+                    let win_id = win_event.window.get_id();
+
+                    let expected = window_event_loop::is_expected(win_id, &win_event.kind);
+
+                    // Changing workspaces minimizes or hides the windows of the previous
+                    // workspace, which must not be mistaken for the user minimizing them.
+                    if state.is_awake() && !expected {
+                        let behaviour = state.config.read().minimize_behaviour;
+
+                        state.with_dsp_containing_win_mut(win_id, |d| {
+                            let config = state.get_display_config(&d.id);
+                            let area = d.get_render_area(&config);
+
+                            let result = match behaviour {
+                                MinimizeBehaviour::Unmanage => {
+                                    d.wm.unmanage(&rt, &config, area, win_id)
+                                }
+                                MinimizeBehaviour::Park => d.wm.park(&rt, &config, area, win_id),
+                            };
+
+                            if let Err(e) = result {
+                                error!(
+                                    "Failed to {} '{}': {:?}",
+                                    behaviour,
+                                    win_event.window.get_title(),
+                                    e
+                                );
+                            }
+
+                            info!("'{}' minimized", win_event.window.get_title());
+                        });
+                    }
+                }
+                WindowEventKind::Restored => {
+                    let win_id = win_event.window.get_id();
+
+                    let expected = window_event_loop::is_expected(win_id, &win_event.kind);
+
+                    if state.is_awake() && !expected {
+                        let dsp_id = state.with_dsp_containing_win_mut(win_id, |d| {
+                            let config = state.get_display_config(&d.id);
+                            let area = d.get_render_area(&config);
+                            if let Err(e) = d.wm.unpark(&rt, &config, area, win_id) {
+                                error!(
+                                    "Failed to unpark '{}': {:?}",
+                                    win_event.window.get_title(),
+                                    e
+                                );
+                            }
+                            info!("'{}' restored", win_event.window.get_title());
+                            d.id.clone()
+                        });
+
+                        if let Some(dsp_id) = dsp_id {
+                            state.set_focused_dsp_id(dsp_id);
+                        }
+                    }
                 }
             },
             Event::RenderBarLayout => {
@@ -375,13 +577,108 @@ fn failable_main() -> Result<(), Error> {
                     action.handle(&state, &rt, &mut notification_manager);
                 }
             }
+            Event::DisplaysChanged => {
+                let new_displays = display::split_displays(
+                    Api::get_displays(),
+                    &state.config.read().display_splits,
+                );
+                let changes =
+                    display::reconcile_displays(&mut state.displays.write(), new_displays);
+
+                if changes.is_empty() {
+                    continue;
+                }
+
+                info!(
+                    "Displays changed (added: {:?}, removed: {:?})",
+                    changes.added, changes.removed
+                );
+
+                // The display that took over the workspaces of the focused display gets focused,
+                // instead of whichever display happens to be first now
+                let focused_target = state.focused_dsp_id.read().as_ref().and_then(|id| {
+                    changes
+                        .targets
+                        .iter()
+                        .find(|(removed_id, _)| removed_id == id)
+                        .map(|(_, target_id)| target_id.clone())
+                });
+
+                if let Some(target_id) = focused_target {
+                    state.set_focused_dsp_id(target_id);
+                }
+
+                let strategy = state.config.read().workspace_switch_strategy;
+
+                for d in state.displays.write().iter_mut() {
+                    for (ws_id, dsp_id) in &changes.migrated {
+                        if &d.id == dsp_id && d.wm.focused_workspace_id != Some(*ws_id) {
+                            if let Some(ws) = d.wm.get_ws_by_id_mut(*ws_id) {
+                                ws.hide(strategy);
+                            }
+                        }
+                    }
+                }
+
+                if state.is_awake() {
+                    state.tx.send(Event::Action(Action::ShowBars)).unwrap();
+
+                    if state.config.read().remove_task_bar {
+                        state.tx.send(Event::Action(Action::HideTaskbars)).unwrap();
+                    }
+
+                    state.tx.send(Event::RenderGraph).unwrap();
+                }
+
+                if let Err(e) = lua::emit_display_changed(
+                    &rt,
+                    LuaEvent::DisplayChanged {
+                        added: changes.added,
+                        removed: changes.removed,
+                    },
+                ) {
+                    error!("{}", lua_error_to_string(e));
+                }
+            }
+            Event::ExpirePendingSlots => {
+                let expired_slots = template::take_expired_slots(&mut state.pending_slots.write());
+
+                for slot in expired_slots {
+                    warn!(
+                        "No window showed up for the slot {} of workspace {}",
+                        slot.description, slot.ws_id.0
+                    );
+
+                    state.with_dsp_containing_ws_mut(slot.ws_id, |d| {
+                        let config = state.get_display_config(&d.id);
+                        let area = d.get_render_area(&config);
+                        d.wm.remove_placeholder(&rt, &config, slot.ws_id, area, slot.placeholder)
+                            .unwrap();
+                    });
+
+                    if let Err(e) = lua::emit_slot_expired(
+                        &rt,
+                        LuaEvent::SlotExpired {
+                            ws_id: slot.ws_id,
+                            session: slot.session,
+                            window: slot.description,
+                        },
+                    ) {
+                        error!("{}", lua_error_to_string(e));
+                    }
+                }
+            }
             Event::RenderGraph => {
                 for d in state.displays.read().iter() {
-                    let area = d.get_render_area(&state.config.read());
-                    d.wm.render(&state.config.read(), area);
+                    let config = state.get_display_config(&d.id);
+                    let area = d.get_render_area(&config);
+                    d.wm.render(&config, area);
                 }
             }
             Event::Exit => {
+                state.stats.write().stop();
+                state.stats.write().flush();
+
                 for d in state.displays.write().iter_mut() {
                     d.show_taskbar();
                     d.wm.cleanup();
@@ -3,7 +3,12 @@ use std::ops;
 
 pub use win::*;
 
-use crate::{display::Display, key::Key, modifiers::Modifiers};
+use crate::{
+    display::{Display, DisplayId},
+    key::Key,
+    launch::LaunchOptions,
+    modifiers::Modifiers,
+};
 
 pub trait NativeWindow: Clone + Copy + std::fmt::Debug {
     fn new(id: WindowId) -> Self;
@@ -20,12 +25,22 @@ pub trait NativeWindow: Clone + Copy + std::fmt::Debug {
     fn remove_decorations(&self) -> Box<dyn Fn() + 'static + Send + Sync>;
     fn get_id(&self) -> WindowId;
     fn get_title(&self) -> String;
+    fn get_class_name(&self) -> String;
+    /// The full path of the executable that created the window
+    fn get_process_path(&self) -> String;
+    /// The file name of the executable that created the window (e.g. `firefox.exe`)
+    fn get_process_name(&self) -> String;
+    fn get_process_id(&self) -> u32;
+    /// The ids of the processes that (indirectly) started the process of the window, beginning
+    /// with its parent
+    fn get_parent_process_ids(&self) -> Vec<u32>;
     fn get_size(&self) -> Size;
     fn get_position(&self) -> Position;
 }
 
 pub trait NativeMonitor {
     fn get_id(&self) -> MonitorId;
+    fn get_area(&self) -> Area;
     fn get_work_area(&self) -> Area;
     // fn get_name() -> String;
 }
@@ -36,9 +51,16 @@ pub trait NativeApi {
 
     /// This function simulates keys presses
     fn simulate_key_press(key: Key, modifiers: Modifiers);
-    fn launch(path: String);
+    /// Starts the program without waiting for it and returns the id of its process. There is no
+    /// process id if the command got handed to an already running program (e.g. a url).
+    fn launch(cmd: &str, options: &LaunchOptions) -> std::io::Result<Option<u32>>;
     fn get_foreground_window() -> Self::Window;
+    /// The visible top-level windows, including the ones nog doesn't manage
+    fn get_windows() -> Vec<Self::Window>;
     fn get_displays() -> Vec<Display>;
+    /// Cheaper version of `get_displays` which is used to detect when displays get connected or
+    /// disconnected.
+    fn get_display_ids() -> Vec<DisplayId>;
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -69,6 +91,13 @@ impl Area {
     pub fn new(size: Size, pos: Position) -> Self {
         Self { size, pos }
     }
+
+    pub fn get_center(&self) -> Position {
+        Position::new(
+            self.pos.x + (self.size.width / 2) as isize,
+            self.pos.y + (self.size.height / 2) as isize,
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -1,20 +1,23 @@
-use std::{mem, os::windows::process::CommandExt, process::Command, ptr};
+use std::{io, mem, process::Command, ptr};
 
 use widestring::WideCString;
 use windows::Windows::Win32::{
-    Foundation::{BOOL, HWND, LPARAM, PWSTR},
+    Foundation::{CloseHandle, BOOL, HWND, LPARAM, PWSTR},
     Graphics::Gdi::{
         EnumDisplayDevicesW, MonitorFromWindow, DISPLAY_DEVICEW,
         DISPLAY_DEVICE_ATTACHED_TO_DESKTOP, MONITOR_DEFAULTTONEAREST,
     },
+    System::Threading::GetProcessId,
     UI::{
         KeyboardAndMouseInput::{
             keybd_event, MapVirtualKeyA, MapVirtualKeyW, SendInput, KEYBD_EVENT_FLAGS,
             KEYEVENTF_KEYUP,
         },
+        Shell::{ShellExecuteExW, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW},
         WindowsAndMessaging::{
-            EnumWindows, GetForegroundWindow, MAPVK_VK_TO_VSC, VK_CONTROL, VK_LWIN, VK_MENU,
-            VK_SHIFT,
+            EnumWindows, GetForegroundWindow, GetWindowLongW, GetWindowTextLengthW,
+            IsWindowVisible, GWL_STYLE, MAPVK_VK_TO_VSC, SW_SHOWNORMAL, VK_CONTROL, VK_LWIN,
+            VK_MENU, VK_SHIFT, WS_CHILD, WS_POPUP,
         },
     },
 };
@@ -22,8 +25,9 @@ use windows::Windows::Win32::{
 use crate::{
     display::{Display, DisplayId},
     key::Key,
+    launch::LaunchOptions,
     modifiers::Modifiers,
-    platform::{Monitor, NativeApi, Window},
+    platform::{Monitor, NativeApi, NativeWindow, Window},
     window_manager::WindowManager,
 };
 
@@ -40,6 +44,32 @@ unsafe extern "system" fn enum_windows_task_bars_cb(hwnd: HWND, l_param: LPARAM)
     true.into()
 }
 
+/// Uses the same filter as the window event hook, so that only windows nog would manage get
+/// collected.
+unsafe extern "system" fn enum_windows_cb(hwnd: HWND, l_param: LPARAM) -> BOOL {
+    let windows = &mut *(l_param.0 as *mut Vec<HWND>);
+    let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+
+    if IsWindowVisible(hwnd).as_bool()
+        && style & (WS_CHILD.0 | WS_POPUP.0) == 0
+        && GetWindowTextLengthW(hwnd) > 0
+    {
+        windows.push(hwnd);
+    }
+
+    true.into()
+}
+
+/// Quotes the argument the way programs parse their command line, in case it contains
+/// whitespace or quotes
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"') {
+        return arg.to_string();
+    }
+
+    format!("\"{}\"", arg.replace('"', "\\\""))
+}
+
 #[derive(Debug)]
 struct DisplayDevice {
     pub name: String,
@@ -103,6 +133,54 @@ impl Api {
         taskbars.into_iter().map(Window::from_hwnd).collect()
     }
 
+    /// Lets the shell open the command like `start` does, which also works for things that
+    /// aren't executables (e.g. urls, documents, shortcuts, `code.cmd` or programs registered in
+    /// App Paths). The environment variables of the options can't be passed this way.
+    fn shell_execute(cmd: &str, options: &LaunchOptions) -> io::Result<Option<u32>> {
+        let to_wide = |s: &str| {
+            WideCString::from_str(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+        };
+
+        let file = to_wide(cmd)?;
+        let params = to_wide(
+            &options
+                .args
+                .iter()
+                .map(|arg| quote_arg(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+        )?;
+        let cwd = options.cwd.as_deref().map(to_wide).transpose()?;
+
+        unsafe {
+            let mut info = SHELLEXECUTEINFOW::default();
+            info.cbSize = mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+            info.fMask = SEE_MASK_NOCLOSEPROCESS;
+            info.lpFile = PWSTR(file.as_ptr() as *mut u16);
+            info.lpParameters = PWSTR(params.as_ptr() as *mut u16);
+            info.lpDirectory = match &cwd {
+                Some(cwd) => PWSTR(cwd.as_ptr() as *mut u16),
+                None => PWSTR(ptr::null_mut()),
+            };
+            info.nShow = SW_SHOWNORMAL.0 as i32;
+
+            if !ShellExecuteExW(&mut info).as_bool() {
+                return Err(io::Error::last_os_error());
+            }
+
+            // The shell doesn't start a new process if an already running program handles the
+            // request (e.g. a url that opens in a new tab)
+            if info.hProcess.0 == 0 {
+                return Ok(None);
+            }
+
+            let pid = GetProcessId(info.hProcess);
+            CloseHandle(info.hProcess);
+
+            Ok(Some(pid).filter(|pid| *pid != 0))
+        }
+    }
+
     unsafe fn send_key(virtual_key: u8, release: bool) {
         let scan_code = MapVirtualKeyW(virtual_key as u32, MAPVK_VK_TO_VSC) as u8;
 
@@ -123,12 +201,22 @@ impl NativeApi for Api {
     type Window = Window;
     type Monitor = Monitor;
 
-    fn launch(path: String) {
-        Command::new("cmd")
-            .arg("/C")
-            .raw_arg(&format!(r#"start "" "{}""#, path))
-            .spawn()
-            .unwrap();
+    fn launch(cmd: &str, options: &LaunchOptions) -> io::Result<Option<u32>> {
+        let mut command = Command::new(cmd);
+
+        command.args(&options.args).envs(&options.env);
+
+        if let Some(cwd) = &options.cwd {
+            command.current_dir(cwd);
+        }
+
+        match command.spawn() {
+            Ok(child) => Ok(Some(child.id())),
+            Err(e) => {
+                log::debug!("Spawning '{}' failed ({}), using the shell instead", cmd, e);
+                Self::shell_execute(cmd, options)
+            }
+        }
     }
     fn simulate_key_press(key: Key, m: Modifiers) {
         unsafe {
@@ -167,6 +255,20 @@ impl NativeApi for Api {
         unsafe { Window::from_hwnd(GetForegroundWindow()) }
     }
 
+    fn get_windows() -> Vec<Self::Window> {
+        let mut windows: Vec<HWND> = Vec::new();
+
+        unsafe {
+            //EnumWindows is synchronous
+            EnumWindows(
+                Some(enum_windows_cb),
+                LPARAM(&mut windows as *mut Vec<HWND> as isize),
+            );
+        }
+
+        windows.into_iter().map(Window::from_hwnd).collect()
+    }
+
     fn get_displays() -> Vec<Display> {
         let devices = Self::get_display_devices();
         assert!(
@@ -191,8 +293,20 @@ impl NativeApi for Api {
                     bar: None,
                     wm: WindowManager::new(),
                     monitor,
+                    region: None,
                 }
             })
             .collect()
     }
+
+    fn get_display_ids() -> Vec<DisplayId> {
+        let mut ids = Self::get_display_devices()
+            .into_iter()
+            .map(|dev| DisplayId(dev.id))
+            .collect::<Vec<_>>();
+
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+
+        ids
+    }
 }
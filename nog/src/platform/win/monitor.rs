@@ -5,7 +5,7 @@ use windows::Windows::Win32::Graphics::Gdi::{
     GetMonitorInfoW, HMONITOR, MONITORINFO, MONITORINFOEXW,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Monitor {
     /// HMONITOR
     pub id: MonitorId,
@@ -46,6 +46,18 @@ impl NativeMonitor for Monitor {
         self.id
     }
 
+    fn get_area(&self) -> Area {
+        let hmonitor = HMONITOR(self.id.0);
+        let mut monitor_info = MONITORINFO::default();
+        monitor_info.cbSize = mem::size_of::<MONITORINFO>() as u32;
+
+        unsafe {
+            GetMonitorInfoW(hmonitor, &mut monitor_info as *mut MONITORINFO);
+        }
+
+        Area::from(monitor_info.rcMonitor)
+    }
+
     /// The work area is the part of the screen that is not occupied by any system windows, like
     /// for example a taskbar
     fn get_work_area(&self) -> Area {
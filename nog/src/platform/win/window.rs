@@ -1,21 +1,30 @@
+use std::collections::HashMap;
 use std::ffi::c_void;
 use std::{mem, ptr};
 
-use windows::Windows::Win32::Foundation::{HWND, LPARAM, PWSTR, RECT, WPARAM};
+use windows::Windows::Win32::Foundation::{
+    CloseHandle, HWND, LPARAM, MAX_PATH, PWSTR, RECT, WPARAM,
+};
 use windows::Windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+};
+use windows::Windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_FORMAT, PROCESS_QUERY_LIMITED_INFORMATION,
+};
 use windows::Windows::Win32::UI::WindowsAndMessaging::{
     GetClassNameW, GetClientRect, GetSystemMetrics, GetWindowLongW, GetWindowPlacement,
-    PostMessageW, SendMessageA, SendMessageW, SendNotifyMessageW, SetWindowLongW,
-    SetWindowPlacement, GWL_EXSTYLE, GWL_STYLE, SC_CLOSE, SC_RESTORE, SM_CYCAPTION, SWP_DRAWFRAME,
-    SWP_FRAMECHANGED, SWP_NOCOPYBITS, SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE, SW_SHOW,
-    SW_SHOWMINIMIZED, WM_SYSCOMMAND, WS_CAPTION, WS_EX_CLIENTEDGE, WS_EX_DLGMODALFRAME,
+    GetWindowThreadProcessId, PostMessageW, SendMessageA, SendMessageW, SendNotifyMessageW,
+    SetWindowLongW, SetWindowPlacement, GWL_EXSTYLE, GWL_STYLE, SC_CLOSE, SC_RESTORE, SM_CYCAPTION,
+    SWP_DRAWFRAME, SWP_FRAMECHANGED, SWP_NOCOPYBITS, SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE,
+    SW_SHOW, SW_SHOWMINIMIZED, WM_SYSCOMMAND, WS_CAPTION, WS_EX_CLIENTEDGE, WS_EX_DLGMODALFRAME,
     WS_EX_STATICEDGE, WS_MAXIMIZE, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_SYSMENU, WS_THICKFRAME,
 };
 use windows::Windows::Win32::UI::{
     KeyboardAndMouseInput::keybd_event,
     WindowsAndMessaging::{
-        GetWindowRect, GetWindowTextLengthW, GetWindowTextW, IsWindow, SetForegroundWindow,
-        SetWindowPos, ShowWindow, SWP_NOMOVE, SWP_NOSIZE,
+        GetWindowRect, GetWindowTextLengthW, GetWindowTextW, IsWindow, IsWindowVisible,
+        SetForegroundWindow, SetWindowPos, ShowWindow, SWP_NOMOVE, SWP_NOSIZE,
     },
 };
 
@@ -49,17 +58,6 @@ impl Window {
         self.0
     }
 
-    pub fn get_class_name(&self) -> String {
-        let len = 20;
-        let mut buffer = vec![0u16; len];
-
-        unsafe {
-            GetClassNameW(self.0, PWSTR(buffer.as_mut_ptr()), len as i32);
-
-            WideCString::from_ptr_str(buffer.as_ptr()).to_string_lossy()
-        }
-    }
-
     /// This function returns the size of the window INCLUDING the extend window frame
     pub fn get_full_size(&self) -> Size {
         unsafe {
@@ -132,6 +130,18 @@ impl Window {
         }
     }
 
+    pub fn is_minimized(&self) -> bool {
+        unsafe {
+            let mut window_placement = Default::default();
+            GetWindowPlacement(self.0, &mut window_placement);
+            window_placement.showCmd == SW_SHOWMINIMIZED
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        unsafe { IsWindowVisible(self.0).as_bool() }
+    }
+
     pub fn restore_placement(&self) {
         unsafe {
             let mut window_placement = Default::default();
@@ -252,6 +262,110 @@ impl NativeWindow for Window {
         }
     }
 
+    fn get_class_name(&self) -> String {
+        // The maximum length of a class name is 256 characters
+        let len = 257;
+        let mut buffer = vec![0u16; len];
+
+        unsafe {
+            GetClassNameW(self.0, PWSTR(buffer.as_mut_ptr()), len as i32);
+
+            WideCString::from_ptr_str(buffer.as_ptr()).to_string_lossy()
+        }
+    }
+
+    fn get_process_path(&self) -> String {
+        unsafe {
+            let process = OpenProcess(
+                PROCESS_QUERY_LIMITED_INFORMATION,
+                false,
+                self.get_process_id(),
+            );
+
+            if process.is_null() {
+                return String::new();
+            }
+
+            let mut len = MAX_PATH;
+            let mut buffer = vec![0u16; len as usize];
+            let success = QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_FORMAT(0),
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+            );
+
+            CloseHandle(process);
+
+            if !success.as_bool() {
+                return String::new();
+            }
+
+            String::from_utf16_lossy(&buffer[..len as usize])
+        }
+    }
+
+    fn get_process_name(&self) -> String {
+        // We only care about the file name and not the whole path
+        self.get_process_path()
+            .rsplit('\\')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn get_process_id(&self) -> u32 {
+        unsafe {
+            let mut process_id = 0;
+            GetWindowThreadProcessId(self.0, &mut process_id);
+            process_id
+        }
+    }
+
+    fn get_parent_process_ids(&self) -> Vec<u32> {
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+
+            if snapshot.is_invalid() {
+                return Vec::new();
+            }
+
+            let mut parents = HashMap::new();
+            let mut entry = PROCESSENTRY32W {
+                dwSize: mem::size_of::<PROCESSENTRY32W>() as u32,
+                ..Default::default()
+            };
+
+            if Process32FirstW(snapshot, &mut entry).as_bool() {
+                loop {
+                    parents.insert(entry.th32ProcessID, entry.th32ParentProcessID);
+
+                    if !Process32NextW(snapshot, &mut entry).as_bool() {
+                        break;
+                    }
+                }
+            }
+
+            CloseHandle(snapshot);
+
+            let mut ids = Vec::new();
+            let mut id = self.get_process_id();
+
+            // Process ids get reused, so the parent of a process might not exist anymore or the
+            // chain can even contain cycles.
+            while let Some(parent) = parents.get(&id).copied() {
+                if parent == 0 || ids.contains(&parent) {
+                    break;
+                }
+
+                ids.push(parent);
+                id = parent;
+            }
+
+            ids
+        }
+    }
+
     fn get_size(&self) -> Size {
         self.get_window_size().unwrap()
     }
@@ -0,0 +1,76 @@
+use regex::Regex;
+
+use crate::{platform::NativeWindow, workspace::WorkspaceId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleAction {
+    /// The window doesn't get managed automatically
+    Ignore,
+    /// The window gets managed without being part of the layout
+    Float,
+    /// The window gets managed in the given workspace
+    Workspace(WorkspaceId),
+    /// The slot gets passed to the layout function, which decides where to place the window
+    LayoutSlot(LayoutSlot),
+}
+
+/// Where the layout function places a window. The master slave layout understands the names
+/// `master` and `slave`, while the manual layout has no named slots and places the window like
+/// any other. Both understand positions, which nog also uses to restore parked windows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayoutSlot {
+    Named(String),
+    /// The position of the window in the layout, starting at 1
+    Position(usize),
+}
+
+/// A matcher matches a window if all of its defined properties match.
+#[derive(Debug, Clone)]
+pub struct WindowMatcher {
+    pub class: Option<String>,
+    pub title_pattern: Option<Regex>,
+    /// The file name of the executable (e.g. `firefox.exe`), which is compared case insensitively
+    pub process: Option<String>,
+}
+
+impl WindowMatcher {
+    pub fn is_empty(&self) -> bool {
+        self.class.is_none() && self.title_pattern.is_none() && self.process.is_none()
+    }
+
+    pub fn matches(&self, win: &impl NativeWindow) -> bool {
+        if let Some(class) = &self.class {
+            if &win.get_class_name() != class {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.title_pattern {
+            if !pattern.is_match(&win.get_title()) {
+                return false;
+            }
+        }
+
+        if let Some(process) = &self.process {
+            if !win.get_process_name().eq_ignore_ascii_case(process) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub matcher: WindowMatcher,
+    pub action: RuleAction,
+}
+
+/// Returns the action of the first rule that matches the window
+pub fn find_action(rules: &[Rule], win: &impl NativeWindow) -> Option<RuleAction> {
+    rules
+        .iter()
+        .find(|rule| rule.matcher.matches(win))
+        .map(|rule| rule.action.clone())
+}
@@ -85,20 +85,37 @@ fn handle_client(
                                 .iter()
                                 .map(|ws| nog_protocol::Workspace {
                                     id: ws.id.0,
+                                    name: ws.display_name.clone(),
                                     layout: ws.layout_name.clone(),
                                     focused_window_id: ws
                                         .get_focused_win()
                                         .map(|win| win.get_id().0),
                                     windows: ws
                                         .windows()
-                                        .map(|id| nog_protocol::Window { id: id.0 })
+                                        .map(|id| nog_protocol::Window {
+                                            id: id.0,
+                                            urgent: ws.is_win_urgent(id),
+                                        })
                                         .collect(),
                                 })
                                 .collect(),
+                            sticky_windows: d
+                                .wm
+                                .sticky_windows
+                                .iter()
+                                .map(|id| nog_protocol::Window {
+                                    id: id.0,
+                                    urgent: false,
+                                })
+                                .collect(),
                         })
                         .collect();
                     serde_json::to_string(&pstate).expect("Serde failed to serialize the state")
                 }
+                Message::GetStats { days } => {
+                    serde_json::to_string(&state.stats.read().get_stats(days))
+                        .expect("Serde failed to serialize the stats")
+                }
                 Message::ExecuteLua { code, print_type } => {
                     let (result_tx, result_rx) = sync_channel(1);
 
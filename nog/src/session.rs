@@ -1,192 +1,710 @@
 //! Our sessions work similar to vim sessions, in that we don't save sessions automatically
 //! ourselves and that we save the layout of each workspace.
 //!
-//! When loading a session we try to re-manage every window.
+//! When loading a session the workspaces of each saved display replace the workspaces of the
+//! display with the same id, or of the focused display if it isn't connected.
 //!
-//! The format of a session file is as follows:
+//! Window ids don't survive a reboot or a restart of the program, which is why every window is
+//! saved together with hints that identify it (see `SessionWindow`). A window that doesn't exist
+//! anymore gets replaced by a window that matches its hints. If there is none, the slot of the
+//! window is reserved until a matching window shows up (see `template::PendingSlot`). Slots
+//! whose window doesn't show up within `SLOT_TIMEOUT` get removed and reported through the
+//! `slot_expired` event. Only tiled windows have a slot, which is why missing floating, parked
+//! and sticky windows don't get restored.
 //!
-//! @workspace <workspace_id> <layout_name> denotes the start of a workspace section
-//! @endworkspace denotes the end of a workspace section
-//!
-//! A workspace section is split up into two paragraphs (seperated by an empty line).
-//!
-//! The first paragraph contains a list of nodes, where each node is the serialized version of a
-//! `GraphNode`.
-//!
-//! The format of a serialized node looks like this: `(node id):(node type)[:(window id)]`
-//!
-//! Example serialized nodes:
-//!
-//! `1:row`
-//! `2:col`
-//! `3:win:348024`
-//!
-//! The second paragraph contains a list of edges, where each edge is the serialized version of a
-//! `GraphEdge`.
-//!
-//! The format of a serialized edge looks like this: `(child node id):(parent node id)`
-//!
-//! Example serialized edges:
-//!
-//! `1:2`
-//! `3:2`
-//! `4:3`
-//!
-//! TODO: support serializing/deserializing multiple displays
+//! A session is stored as json. The `version` field describes the format of the file, so that
+//! future versions of nog can still read older sessions. Sessions of the legacy text format
+//! (`@workspace <workspace_id> <layout_name>` sections) get imported as well.
 
+use crate::display::Display;
 use crate::graph::{Graph, GraphNode, GraphNodeGroupKind, GraphNodeId};
+use crate::launch::LaunchCommand;
 use crate::paths::get_config_path;
-use crate::platform::WindowId;
-use crate::workspace::{Workspace, WorkspaceId};
-use std::collections::HashMap;
+use crate::platform::{NativeWindow, Window, WindowId};
+use crate::rule::WindowMatcher;
+use crate::workspace::{Workspace, WorkspaceId, WorkspaceState};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The version of the session format nog writes
+pub const SESSION_VERSION: u32 = 1;
+
+/// How long the placeholders of missing windows wait for their window. Restoring a session
+/// starts many programs at once or leaves starting them to the user, which takes longer than
+/// launching a single program.
+pub const SLOT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug)]
+pub struct SessionError {
+    pub path: PathBuf,
+    /// The line the error occurred on, starting at 1
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.path.display(), line, self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+pub type SessionResult<T = ()> = Result<T, SessionError>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SessionNode {
+    Row {
+        /// The index of the child which has focus
+        focus: usize,
+        children: Vec<SessionNode>,
+    },
+    Col {
+        focus: usize,
+        children: Vec<SessionNode>,
+    },
+    Window {
+        id: usize,
+    },
+}
 
-use itertools::Itertools;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParkedWindow {
+    pub id: usize,
+    /// See `Workspace::parked_windows`
+    pub slot: usize,
+}
+
+/// Describes a window well enough to find it again after its id changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub id: usize,
+    /// The full path of the executable
+    pub process: String,
+    pub class: String,
+    /// Windows with the same title are preferred when several windows match
+    pub title: String,
+    /// Can be set by hand to tell windows of the same program apart
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+    /// The program that gets launched if the window is missing, which defaults to the executable
+    #[serde(default)]
+    pub cmd: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl SessionWindow {
+    /// The command is only known if nog launched the window
+    pub fn from_window(win: &impl NativeWindow, command: Option<&LaunchCommand>) -> Self {
+        Self {
+            id: win.get_id().0,
+            process: win.get_process_path(),
+            class: win.get_class_name(),
+            title: win.get_title(),
+            title_pattern: None,
+            cmd: command.map(|command| command.cmd.clone()),
+            args: command
+                .map(|command| command.args.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn launch_command(&self) -> Option<LaunchCommand> {
+        self.cmd.clone().map(|cmd| LaunchCommand {
+            cmd,
+            args: self.args.clone(),
+        })
+    }
+
+    pub fn matcher(&self) -> WindowMatcher {
+        WindowMatcher {
+            class: Some(self.class.clone()),
+            title_pattern: self
+                .title_pattern
+                .as_ref()
+                .and_then(|pattern| Regex::new(pattern).ok()),
+            process: self
+                .process
+                .rsplit('\\')
+                .next()
+                .map(|name| name.to_string()),
+        }
+    }
+
+    pub fn get_cmd(&self) -> &str {
+        self.cmd.as_deref().unwrap_or(&self.process)
+    }
+}
+
+impl std::fmt::Display for SessionWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' ({})", self.title, self.process)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWorkspace {
+    pub id: usize,
+    /// Only set if the user named the workspace
+    #[serde(default)]
+    pub name: Option<String>,
+    pub layout: String,
+    #[serde(default)]
+    pub fullscreen: bool,
+    #[serde(default)]
+    pub persistent: bool,
+    #[serde(default)]
+    pub floating_windows: Vec<usize>,
+    #[serde(default)]
+    pub parked_windows: Vec<ParkedWindow>,
+    pub root: SessionNode,
+}
 
-pub fn save_session(name: &str, workspaces: &[Workspace]) {
-    let session = workspaces
-        .iter()
-        .map(|workspace| {
-            let node_section = workspace
-                .graph
-                .nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDisplay {
+    /// `None` means that the workspaces belong to the focused display (e.g. legacy sessions)
+    pub id: Option<String>,
+    pub focused_workspace_id: Option<usize>,
+    #[serde(default)]
+    pub sticky_windows: Vec<usize>,
+    pub workspaces: Vec<SessionWorkspace>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    #[serde(default)]
+    pub focused_display_id: Option<String>,
+    #[serde(default)]
+    pub workspace_order: Vec<usize>,
+    pub displays: Vec<SessionDisplay>,
+    /// The hints of every window the session contains
+    #[serde(default)]
+    pub windows: Vec<SessionWindow>,
+}
+
+impl Session {
+    pub fn new(
+        displays: &[Display],
+        focused_display_id: &str,
+        order: &[WorkspaceId],
+        launch_commands: &HashMap<WindowId, LaunchCommand>,
+    ) -> Self {
+        Self {
+            version: SESSION_VERSION,
+            focused_display_id: Some(focused_display_id.to_string()),
+            workspace_order: order.iter().map(|id| id.0).collect(),
+            displays: displays
                 .iter()
-                .map(|(node_id, node)| match node {
-                    GraphNode::Group { kind, .. } => format!(
-                        "{}:{}",
-                        node_id,
-                        match kind {
-                            GraphNodeGroupKind::Row => "row",
-                            GraphNodeGroupKind::Col => "col",
-                        }
-                    ),
-                    GraphNode::Window(win_id) => format!("{}:win:{}", node_id, win_id),
+                .map(|d| SessionDisplay {
+                    id: Some(d.id.0.clone()),
+                    focused_workspace_id: d.wm.focused_workspace_id.map(|id| id.0),
+                    sticky_windows: d.wm.sticky_windows.iter().map(|id| id.0).collect(),
+                    workspaces: d
+                        .wm
+                        .workspaces
+                        .iter()
+                        .map(SessionWorkspace::from_workspace)
+                        .collect(),
                 })
-                .join("\n");
+                .collect(),
+            windows: displays
+                .iter()
+                .flat_map(|d| {
+                    d.wm.workspaces
+                        .iter()
+                        .flat_map(|ws| {
+                            ws.visible_windows()
+                                .chain(ws.parked_windows.keys().copied())
+                                .collect::<Vec<_>>()
+                        })
+                        .chain(d.wm.sticky_windows.iter().copied())
+                        .collect::<Vec<_>>()
+                })
+                .map(|id| SessionWindow::from_window(&Window::new(id), launch_commands.get(&id)))
+                .collect(),
+        }
+    }
+
+    /// The windows of the graphs, together with the id of their workspace
+    pub fn get_layout_windows(&self) -> Vec<(WorkspaceId, WindowId)> {
+        let mut windows = Vec::new();
+
+        for ws in self.displays.iter().flat_map(|d| d.workspaces.iter()) {
+            let mut ids = Vec::new();
+            collect_session_windows(&ws.root, &mut ids);
+            windows.extend(ids.into_iter().map(|id| (WorkspaceId(ws.id), id)));
+        }
+
+        windows
+    }
+
+    /// Every window the session contains, including floating, parked and sticky windows
+    pub fn get_window_ids(&self) -> Vec<WindowId> {
+        let mut ids: Vec<WindowId> = self
+            .get_layout_windows()
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect();
+
+        for d in &self.displays {
+            for ws in &d.workspaces {
+                ids.extend(ws.floating_windows.iter().map(|id| WindowId(*id)));
+                ids.extend(ws.parked_windows.iter().map(|parked| WindowId(parked.id)));
+            }
+
+            ids.extend(d.sticky_windows.iter().map(|id| WindowId(*id)));
+        }
+
+        ids
+    }
+
+    pub fn get_window(&self, id: WindowId) -> Option<&SessionWindow> {
+        self.windows.iter().find(|win| win.id == id.0)
+    }
+
+    /// Maps the ids of the saved windows to the ids of the windows that exist now. A window
+    /// keeps its id as long as it still belongs to the same program (e.g. when only nog got
+    /// restarted). Otherwise one of the candidates that matches the hints of the window takes
+    /// its place, where candidates with the same title are preferred.
+    ///
+    /// Windows of legacy sessions don't have any hints, so they only keep their id if they
+    /// still exist.
+    pub fn match_windows<W: NativeWindow>(&self, candidates: &[W]) -> HashMap<WindowId, WindowId> {
+        let mut matched = HashMap::new();
+        let mut claimed = HashSet::new();
+
+        for id in self.get_window_ids() {
+            let win = W::new(id);
+            let same_program = self
+                .get_window(id)
+                .map(|hints| hints.matcher().matches(&win))
+                .unwrap_or(true);
+
+            if win.exists() && same_program {
+                matched.insert(id, id);
+                claimed.insert(id);
+            }
+        }
+
+        for hints in &self.windows {
+            let id = WindowId(hints.id);
+
+            if matched.contains_key(&id) {
+                continue;
+            }
 
-            let edge_section = workspace
-                .graph
-                .edges
+            let matcher = hints.matcher();
+            let matching: Vec<&W> = candidates
                 .iter()
-                .map(|edge| format!("{}:{}", edge.child, edge.parent))
-                .join("\n");
+                .filter(|win| !claimed.contains(&win.get_id()) && matcher.matches(*win))
+                .collect();
 
-            let sections = [node_section, edge_section]
+            let found = matching
                 .iter()
-                .filter(|s| !s.is_empty())
-                .join("\n\n");
+                .find(|win| win.get_title() == hints.title)
+                .or_else(|| matching.first());
 
-            format!(
-                "@workspace {} {}\n{}\n@endworkspace",
-                workspace.id.0, &workspace.layout_name, sections
-            )
-        })
-        .join("\n");
+            if let Some(win) = found {
+                matched.insert(id, win.get_id());
+                claimed.insert(win.get_id());
+            }
+        }
 
-    let mut path = get_config_path();
-    path.push("sessions");
+        matched
+    }
+}
+
+fn collect_session_windows(node: &SessionNode, ids: &mut Vec<WindowId>) {
+    match node {
+        SessionNode::Window { id } => ids.push(WindowId(*id)),
+        SessionNode::Row { children, .. } | SessionNode::Col { children, .. } => {
+            for child in children {
+                collect_session_windows(child, ids);
+            }
+        }
+    }
+}
 
-    if !path.exists() {
-        fs::create_dir(&path).unwrap();
+impl SessionWorkspace {
+    pub fn from_workspace(ws: &Workspace) -> Self {
+        Self {
+            id: ws.id.0,
+            name: Some(ws.display_name.clone()).filter(|_| ws.has_custom_name()),
+            layout: ws.layout_name.clone(),
+            fullscreen: ws.is_fullscreen(),
+            persistent: ws.persistent,
+            floating_windows: ws.floating_windows.iter().map(|id| id.0).collect(),
+            parked_windows: ws
+                .parked_windows
+                .iter()
+                .map(|(id, slot)| ParkedWindow {
+                    id: id.0,
+                    slot: *slot,
+                })
+                .collect(),
+            root: session_node_from_graph(&ws.graph, ws.graph.root_node_id),
+        }
     }
 
-    path.push(name);
+    /// Creates the workspace, where `resolve` returns the id a saved window has now (see
+    /// `Session::match_windows`). Windows it doesn't return an id for are left out.
+    pub fn to_workspace(&self, resolve: impl Fn(WindowId) -> Option<WindowId>) -> Workspace {
+        let mut ws = Workspace::new(WorkspaceId(self.id), &self.layout);
+
+        if let Some(name) = &self.name {
+            ws.set_name(name.clone());
+        }
+
+        if self.fullscreen {
+            ws.state = WorkspaceState::Fullscreen;
+        }
 
-    fs::write(path, session).unwrap();
+        ws.persistent = self.persistent;
+        ws.floating_windows = self
+            .floating_windows
+            .iter()
+            .filter_map(|id| resolve(WindowId(*id)))
+            .collect();
+        ws.parked_windows = self
+            .parked_windows
+            .iter()
+            .filter_map(|parked| resolve(WindowId(parked.id)).map(|id| (id, parked.slot)))
+            .collect();
+
+        let root_id = ws.graph.root_node_id;
+
+        match &self.root {
+            SessionNode::Row { focus, children } | SessionNode::Col { focus, children } => {
+                ws.graph.nodes.insert(
+                    root_id,
+                    GraphNode::Group {
+                        kind: match self.root {
+                            SessionNode::Row { .. } => GraphNodeGroupKind::Row,
+                            _ => GraphNodeGroupKind::Col,
+                        },
+                        focus: 0,
+                        child_count: 0,
+                    },
+                );
+
+                for child in children {
+                    add_session_node(&mut ws.graph, root_id, child, &resolve);
+                }
+
+                set_focus(&mut ws.graph, root_id, *focus);
+            }
+            SessionNode::Window { .. } => {}
+        }
+
+        ws.graph.dirty = false;
+        ws
+    }
 }
 
-pub fn load_session(name: &str) -> Option<Vec<Workspace>> {
+fn session_node_from_graph(graph: &Graph, id: GraphNodeId) -> SessionNode {
+    match graph.get_node(id) {
+        Some(GraphNode::Group { kind, focus, .. }) => {
+            let children = graph
+                .get_children(id)
+                .into_iter()
+                .map(|child| session_node_from_graph(graph, child))
+                .collect();
+
+            match kind {
+                GraphNodeGroupKind::Row => SessionNode::Row {
+                    focus: *focus,
+                    children,
+                },
+                GraphNodeGroupKind::Col => SessionNode::Col {
+                    focus: *focus,
+                    children,
+                },
+            }
+        }
+        Some(GraphNode::Window(win_id)) => SessionNode::Window { id: win_id.0 },
+        None => unreachable!("The children of a node have to exist"),
+    }
+}
+
+/// Adds the node to the graph, leaving out windows that couldn't be resolved. Groups keep their
+/// saved focus if the focused child still exists.
+fn add_session_node(
+    graph: &mut Graph,
+    parent: GraphNodeId,
+    node: &SessionNode,
+    resolve: &impl Fn(WindowId) -> Option<WindowId>,
+) {
+    match node {
+        SessionNode::Window { id } => {
+            if let Some(id) = resolve(WindowId(*id)) {
+                graph.add_window(parent, id).unwrap();
+            }
+        }
+        SessionNode::Row { focus, children } | SessionNode::Col { focus, children } => {
+            let id = match node {
+                SessionNode::Row { .. } => graph.add_row(parent),
+                _ => graph.add_col(parent),
+            }
+            .unwrap();
+
+            for child in children {
+                add_session_node(graph, id, child, resolve);
+            }
+
+            set_focus(graph, id, *focus);
+        }
+    }
+}
+
+fn set_focus(graph: &mut Graph, id: GraphNodeId, value: usize) {
+    if let Some(GraphNode::Group {
+        focus, child_count, ..
+    }) = graph.get_node_mut(id)
+    {
+        *focus = value.min(child_count.saturating_sub(1));
+    }
+}
+
+fn get_session_path(name: &str) -> PathBuf {
     let mut path = get_config_path();
     path.push("sessions");
     path.push(name);
+    path
+}
 
-    if !path.exists() {
-        return None;
+pub fn save_session(name: &str, session: &Session) -> SessionResult {
+    let path = get_session_path(name);
+    let error = |message: String| SessionError {
+        path: path.clone(),
+        line: None,
+        message,
+    };
+
+    let content = serde_json::to_string_pretty(session).map_err(|e| error(e.to_string()))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| error(e.to_string()))?;
+    }
+
+    fs::write(&path, content).map_err(|e| error(e.to_string()))
+}
+
+pub fn load_session(name: &str) -> SessionResult<Session> {
+    let path = get_session_path(name);
+    let content = fs::read_to_string(&path).map_err(|e| SessionError {
+        path: path.clone(),
+        line: None,
+        message: e.to_string(),
+    })?;
+
+    if content.trim_start().starts_with('{') {
+        parse_session(&path, &content)
+    } else {
+        parse_legacy_session(&path, &content)
+    }
+}
+
+fn parse_session(path: &Path, content: &str) -> SessionResult<Session> {
+    let session: Session = serde_json::from_str(content).map_err(|e| SessionError {
+        path: path.to_path_buf(),
+        line: Some(e.line()),
+        message: e.to_string(),
+    })?;
+
+    if session.version > SESSION_VERSION {
+        return Err(SessionError {
+            path: path.to_path_buf(),
+            line: None,
+            message: format!(
+                "The session has version {}, but this version of nog only supports version {}",
+                session.version, SESSION_VERSION
+            ),
+        });
+    }
+
+    for win in &session.windows {
+        if let Some(pattern) = &win.title_pattern {
+            if let Err(e) = Regex::new(pattern) {
+                return Err(SessionError {
+                    path: path.to_path_buf(),
+                    line: None,
+                    message: format!("The title pattern of window {} is invalid: {}", win.id, e),
+                });
+            }
+        }
     }
 
-    let content = fs::read_to_string(path).unwrap();
-    let lines: Vec<&str> = content.split('\n').collect();
+    Ok(session)
+}
+
+/// Reads the text format older versions of nog wrote, which looks as follows:
+///
+/// @order <workspace_id>... denotes the user defined order of the workspaces (optional)
+/// @workspace <workspace_id> <layout_name> denotes the start of a workspace section
+/// @endworkspace denotes the end of a workspace section
+///
+/// A workspace section is split up into a paragraph of nodes (`(node id):(row|col)` or
+/// `(node id):win:(window id)`) and a paragraph of edges (`(child node id):(parent node id)`),
+/// seperated by an empty line.
+fn parse_legacy_session(path: &Path, content: &str) -> SessionResult<Session> {
+    let error = |line: usize, message: String| SessionError {
+        path: path.to_path_buf(),
+        line: Some(line + 1),
+        message,
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
     let mut i = 0;
 
     let mut workspaces = Vec::new();
+    let mut workspace_order = Vec::new();
 
     while i < lines.len() {
         let line = lines[i];
 
+        if let Some(rest) = line.strip_prefix("@order") {
+            for token in rest.split_whitespace() {
+                workspace_order.push(
+                    token
+                        .parse::<usize>()
+                        .map_err(|_| error(i, format!("Invalid workspace id '{}'", token)))?,
+                );
+            }
+        }
+
         if let Some(rest) = line.strip_prefix("@workspace") {
-            let tokens = rest.trim().split(' ').collect::<Vec<_>>();
-            let id = WorkspaceId(tokens[0].parse::<usize>().ok()?);
-            let layout_name = tokens[1];
-            let mut graph = Graph {
-                max_id: 0,
-                dirty: false,
-                root_node_id: 0,
-                nodes: HashMap::new(),
-                edges: Vec::new(),
+            let (id, layout) = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [id, layout] => (
+                    id.parse::<usize>()
+                        .map_err(|_| error(i, format!("Invalid workspace id '{}'", id)))?,
+                    layout.to_string(),
+                ),
+                _ => {
+                    return Err(error(
+                        i,
+                        String::from("Expected '@workspace <workspace_id> <layout_name>'"),
+                    ))
+                }
             };
 
+            let mut nodes: HashMap<usize, SessionNode> = HashMap::new();
+            let mut edges: Vec<(usize, usize)> = Vec::new();
+
             i += 1;
 
             while i < lines.len() && !lines[i].is_empty() && lines[i] != "@endworkspace" {
-                let line = lines[i];
-                let parts = line.split(':').collect::<Vec<&str>>();
+                let parts = lines[i].split(':').collect::<Vec<&str>>();
+                let parse_id = |value: &str| {
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| error(i, format!("Invalid id '{}'", value)))
+                };
 
                 let (id, node) = match parts.as_slice() {
-                    [node_id, "row"] => (
-                        node_id,
-                        GraphNode::Group {
-                            kind: GraphNodeGroupKind::Row,
-                            child_count: 0,
+                    [id, "row"] => (
+                        parse_id(id)?,
+                        SessionNode::Row {
                             focus: 0,
+                            children: Vec::new(),
                         },
                     ),
-                    [node_id, "col"] => (
-                        node_id,
-                        GraphNode::Group {
-                            kind: GraphNodeGroupKind::Col,
-                            child_count: 0,
+                    [id, "col"] => (
+                        parse_id(id)?,
+                        SessionNode::Col {
                             focus: 0,
+                            children: Vec::new(),
                         },
                     ),
-                    [node_id, "win", win_id] => (
-                        node_id,
-                        GraphNode::Window(WindowId(win_id.parse::<usize>().unwrap())),
+                    [id, "win", win_id] => (
+                        parse_id(id)?,
+                        SessionNode::Window {
+                            id: parse_id(win_id)?,
+                        },
                     ),
-                    _ => unreachable!("{:?}", line),
+                    _ => return Err(error(i, format!("Invalid node '{}'", lines[i]))),
                 };
 
-                let id = id.parse::<GraphNodeId>().unwrap();
-                if id > graph.max_id {
-                    graph.max_id = id;
-                }
-                graph.nodes.insert(id, node);
+                nodes.insert(id, node);
 
                 i += 1;
             }
 
-            i += 1;
+            if i < lines.len() && lines[i].is_empty() {
+                i += 1;
+            }
 
             while i < lines.len() && lines[i] != "@endworkspace" {
-                let line = lines[i];
-                let parts = line.split(':').collect::<Vec<&str>>();
-
-                let child = parts[0].parse::<GraphNodeId>().unwrap();
-                let parent = parts[1].parse::<GraphNodeId>().unwrap();
-
-                graph.add_edge(parent, child);
+                match lines[i].split(':').collect::<Vec<&str>>().as_slice() {
+                    [child, parent] => match (child.parse::<usize>(), parent.parse::<usize>()) {
+                        (Ok(child), Ok(parent)) => edges.push((child, parent)),
+                        _ => return Err(error(i, format!("Invalid edge '{}'", lines[i]))),
+                    },
+                    _ => return Err(error(i, format!("Invalid edge '{}'", lines[i]))),
+                }
 
                 i += 1;
             }
 
-            //TODO: Correctly set the `child_count` of group nodes
+            if i == lines.len() {
+                return Err(error(i - 1, String::from("Missing '@endworkspace'")));
+            }
 
-            let mut workspace = Workspace::new(id, layout_name);
-            workspace.graph = graph;
-            workspaces.push(workspace);
+            let root = build_legacy_node(0, &mut nodes, &edges).ok_or_else(|| {
+                error(
+                    i,
+                    format!("Workspace {} doesn't have a valid root node", id),
+                )
+            })?;
+
+            workspaces.push(SessionWorkspace {
+                id,
+                name: None,
+                layout,
+                fullscreen: false,
+                persistent: false,
+                floating_windows: Vec::new(),
+                parked_windows: Vec::new(),
+                root,
+            });
         }
 
         i += 1;
     }
 
-    Some(workspaces)
+    Ok(Session {
+        version: SESSION_VERSION,
+        focused_display_id: None,
+        workspace_order,
+        displays: vec![SessionDisplay {
+            id: None,
+            focused_workspace_id: None,
+            sticky_windows: Vec::new(),
+            workspaces,
+        }],
+        windows: Vec::new(),
+    })
+}
+
+fn build_legacy_node(
+    id: usize,
+    nodes: &mut HashMap<usize, SessionNode>,
+    edges: &[(usize, usize)],
+) -> Option<SessionNode> {
+    let mut node = nodes.remove(&id)?;
+
+    if let SessionNode::Row { children, .. } | SessionNode::Col { children, .. } = &mut node {
+        for (child, _) in edges.iter().filter(|(_, parent)| *parent == id) {
+            children.push(build_legacy_node(*child, nodes, edges)?);
+        }
+    }
+
+    Some(node)
 }
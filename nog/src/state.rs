@@ -1,20 +1,28 @@
 use crate::{
-    config::Config,
-    display::{Display, DisplayId},
+    config::{Config, DisplayConfigView},
+    direction::Direction,
+    display::{self, Display, DisplayId, DisplayTarget},
     event::Event,
     keybinding::Keybinding,
-    platform::WindowId,
+    launch::{LaunchCommand, PendingLaunch},
+    platform::{NativeMonitor, WindowId},
+    rule::Rule,
+    stats::StatsRecorder,
+    template::{PendingSlot, WorkspaceTemplate},
     thread_safe::ThreadSafe,
     workspace::{Workspace, WorkspaceId},
 };
 use nog_protocol::BarContent;
-use std::sync::mpsc::SyncSender;
+use std::{
+    collections::HashMap,
+    sync::{mpsc::SyncSender, Arc},
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum StateMode {
     Awake,
     Hibernating,
-    Initializing
+    Initializing,
 }
 
 /// You can clone the state without any worries.
@@ -23,9 +31,18 @@ pub struct State {
     pub mode: ThreadSafe<StateMode>,
     pub tx: SyncSender<Event>,
     pub displays: ThreadSafe<Vec<Display>>,
+    /// `None` means that the first display is focused
+    pub focused_dsp_id: ThreadSafe<Option<DisplayId>>,
     pub bar_content: ThreadSafe<BarContent>,
     pub keybindings: ThreadSafe<Vec<Keybinding>>,
-    pub config: ThreadSafe<Config>,
+    pub rules: ThreadSafe<Vec<Rule>>,
+    pub pending_launches: ThreadSafe<Vec<PendingLaunch>>,
+    pub templates: ThreadSafe<HashMap<String, WorkspaceTemplate>>,
+    pub pending_slots: ThreadSafe<Vec<PendingSlot>>,
+    /// The commands nog launched windows with, so that sessions can launch them again
+    pub launch_commands: ThreadSafe<HashMap<WindowId, LaunchCommand>>,
+    pub stats: ThreadSafe<StatsRecorder>,
+    pub config: ThreadSafe<Arc<Config>>,
 }
 
 impl State {
@@ -34,7 +51,14 @@ impl State {
             mode: ThreadSafe::new(StateMode::Initializing),
             tx,
             displays: Default::default(),
+            focused_dsp_id: Default::default(),
             keybindings: Default::default(),
+            rules: Default::default(),
+            pending_launches: Default::default(),
+            templates: Default::default(),
+            pending_slots: Default::default(),
+            launch_commands: Default::default(),
+            stats: Default::default(),
             bar_content: Default::default(),
             config: Default::default(),
         }
@@ -82,16 +106,100 @@ impl State {
             .map(f)
     }
 
+    /// Returns the index of the focused display inside of `displays`
+    fn get_focused_dsp_idx(&self, displays: &[Display]) -> usize {
+        self.focused_dsp_id
+            .read()
+            .as_ref()
+            .and_then(|id| displays.iter().position(|d| &d.id == id))
+            .unwrap_or(0)
+    }
+
     pub fn with_focused_dsp<T>(&self, f: impl Fn(&Display) -> T) -> T {
-        f(&self.displays.read()[0])
+        let displays = self.displays.read();
+        f(&displays[self.get_focused_dsp_idx(&displays)])
+    }
+
+    pub fn with_focused_dsp_mut<T>(&self, f: impl FnOnce(&mut Display) -> T) -> T {
+        let mut displays = self.displays.write();
+        let idx = self.get_focused_dsp_idx(&displays);
+        f(&mut displays[idx])
+    }
+
+    /// Returns the ids of every existing workspace and every workspace that has a user defined
+    /// position, in order.
+    pub fn get_ws_order(&self) -> Vec<WorkspaceId> {
+        let ws_ids = self
+            .displays
+            .read()
+            .iter()
+            .flat_map(|d| d.wm.workspaces.iter().map(|ws| ws.id))
+            .collect::<Vec<_>>();
+
+        let config = self.config.read();
+        let mut order = config.workspace_order.clone();
+
+        for id in ws_ids {
+            if !order.contains(&id) {
+                order.push(id);
+            }
+        }
+
+        order.sort_by_key(|id| config.get_ws_order_key(*id));
+        order
+    }
+
+    pub fn set_ws_order(&self, order: Vec<WorkspaceId>) {
+        Arc::make_mut(&mut self.config.write()).workspace_order = order;
+
+        let config = self.config.read().clone();
+
+        for d in self.displays.write().iter_mut() {
+            d.wm.sort_workspaces(&config);
+        }
     }
 
-    pub fn with_focused_dsp_mut<T>(&self, f: impl Fn(&mut Display) -> T) -> T {
-        f(&mut self.displays.write()[0])
+    /// The config with the overrides of the display applied
+    pub fn get_display_config(&self, id: &DisplayId) -> DisplayConfigView {
+        DisplayConfigView::new(self.config.read().clone(), id)
     }
 
     pub fn get_focused_dsp_id(&self) -> DisplayId {
-        self.displays.read()[0].id.clone()
+        self.with_focused_dsp(|dsp| dsp.id.clone())
+    }
+
+    pub fn set_focused_dsp_id(&self, id: DisplayId) {
+        *self.focused_dsp_id.write() = Some(id);
+    }
+
+    /// Returns the closest display in the given direction, based on the work area of the
+    /// displays.
+    pub fn get_dsp_in_direction(&self, id: &DisplayId, dir: Direction) -> Option<DisplayId> {
+        let displays = self.displays.read();
+        let from = displays.iter().find(|d| &d.id == id)?.get_work_area();
+        let areas = displays
+            .iter()
+            .map(|d| d.get_work_area())
+            .collect::<Vec<_>>();
+
+        display::find_area_in_direction(from, &areas, dir).map(|idx| displays[idx].id.clone())
+    }
+
+    /// Returns `None` if the display doesn't exist
+    pub fn resolve_dsp_target(&self, from: &DisplayId, target: DisplayTarget) -> Option<DisplayId> {
+        match target {
+            DisplayTarget::Id(id) => self.with_dsp(id.clone(), |_| ()).map(|_| id),
+            DisplayTarget::Direction(dir) => self.get_dsp_in_direction(from, dir),
+        }
+    }
+
+    /// Returns the smallest workspace id that isn't used on any display
+    pub fn get_free_ws_id(&self) -> WorkspaceId {
+        let displays = self.displays.read();
+        (1..)
+            .map(WorkspaceId)
+            .find(|id| displays.iter().all(|d| d.wm.get_ws_by_id(*id).is_none()))
+            .unwrap()
     }
 
     pub fn get_focused_ws_id(&self) -> Option<WorkspaceId> {
@@ -104,7 +212,7 @@ impl State {
     }
 
     /// Doesn't call the function if display doesn't exist
-    pub fn with_dsp_mut<T>(&self, id: DisplayId, f: impl Fn(&mut Display) -> T) -> Option<T> {
+    pub fn with_dsp_mut<T>(&self, id: DisplayId, f: impl FnOnce(&mut Display) -> T) -> Option<T> {
         self.displays.write().iter_mut().find(|d| d.id == id).map(f)
     }
 
@@ -0,0 +1,218 @@
+//! Keeps track of how long the windows of each application had focus, per workspace and per day.
+//!
+//! The stats of a day get stored as json in the `stats` directory of the config path (e.g.
+//! `stats/2021-08-14.json`). An interval that spans midnight counts towards the day it ended on.
+
+use chrono::{Local, NaiveDate};
+use nog_protocol::{AppStats, DayStats, Stats, WorkspaceStats};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{paths::get_config_path, workspace::WorkspaceId};
+
+/// How often the stats get written to disk while the focus changes
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The most days `get_stats` looks at, since every day that isn't in memory has to be read from
+/// disk
+const MAX_STATS_DAYS: u32 = 366;
+
+/// The focus time of each application, keyed by the id of the workspace
+type DayRecord = BTreeMap<usize, BTreeMap<String, Duration>>;
+
+#[derive(Debug)]
+struct FocusInterval {
+    app: String,
+    ws_id: WorkspaceId,
+    started_at: Instant,
+}
+
+#[derive(Debug)]
+pub struct StatsRecorder {
+    current: Option<FocusInterval>,
+    /// The days that got loaded or recorded, keyed by their date (`YYYY-MM-DD`)
+    days: HashMap<String, DayRecord>,
+    /// The days that changed since they got written to disk
+    dirty_days: HashSet<String>,
+    last_flush: Instant,
+}
+
+impl Default for StatsRecorder {
+    fn default() -> Self {
+        Self {
+            current: None,
+            days: HashMap::new(),
+            dirty_days: HashSet::new(),
+            last_flush: Instant::now(),
+        }
+    }
+}
+
+impl StatsRecorder {
+    /// Starts measuring the focus time of the application, unless it already has focus in the
+    /// workspace
+    pub fn focus(&mut self, app: String, ws_id: WorkspaceId) {
+        if let Some(current) = &self.current {
+            if current.app == app && current.ws_id == ws_id {
+                return;
+            }
+        }
+
+        self.stop();
+
+        self.current = Some(FocusInterval {
+            app,
+            ws_id,
+            started_at: Instant::now(),
+        });
+
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// Stops measuring, which is used when nothing nog manages has focus
+    pub fn stop(&mut self) {
+        if let Some(interval) = self.current.take() {
+            let date = format_date(today());
+            let elapsed = interval.started_at.elapsed();
+
+            *self
+                .get_day_mut(&date)
+                .entry(interval.ws_id.0)
+                .or_default()
+                .entry(interval.app)
+                .or_default() += elapsed;
+
+            self.dirty_days.insert(date);
+        }
+    }
+
+    /// Writes the days that changed to disk
+    pub fn flush(&mut self) {
+        for date in self.dirty_days.drain() {
+            if let Some(record) = self.days.get(&date) {
+                if let Err(e) = save_day(&to_day_stats(&date, record)) {
+                    log::error!("Failed to save the stats of {}: {}", date, e);
+                }
+            }
+        }
+
+        self.last_flush = Instant::now();
+    }
+
+    /// Returns the stats of the last `days` days including today (at most `MAX_STATS_DAYS`),
+    /// leaving out days without any stats. The application that currently has focus is included
+    /// as well. Days that aren't in memory are read from disk without keeping them around.
+    pub fn get_stats(&self, days: u32) -> Stats {
+        let today = today();
+        let mut stats = Stats::default();
+
+        for offset in 0..days.min(MAX_STATS_DAYS) {
+            let date = format_date(today - chrono::Duration::days(offset as i64));
+            let mut record = match self.days.get(&date) {
+                Some(record) => record.clone(),
+                None => load_day(&date).unwrap_or_default(),
+            };
+
+            if offset == 0 {
+                if let Some(interval) = &self.current {
+                    *record
+                        .entry(interval.ws_id.0)
+                        .or_default()
+                        .entry(interval.app.clone())
+                        .or_default() += interval.started_at.elapsed();
+                }
+            }
+
+            if !record.is_empty() {
+                stats.days.push(to_day_stats(&date, &record));
+            }
+        }
+
+        stats
+    }
+
+    /// Loads the day from disk if it wasn't loaded yet, so that recording doesn't overwrite the
+    /// stats of a previous run
+    fn get_day_mut(&mut self, date: &str) -> &mut DayRecord {
+        self.days
+            .entry(date.to_string())
+            .or_insert_with(|| load_day(date).unwrap_or_default())
+    }
+}
+
+fn today() -> NaiveDate {
+    Local::now().date().naive_local()
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn get_day_path(date: &str) -> PathBuf {
+    let mut path = get_config_path();
+    path.push("stats");
+    path.push(format!("{}.json", date));
+    path
+}
+
+fn to_day_stats(date: &str, record: &DayRecord) -> DayStats {
+    DayStats {
+        date: date.to_string(),
+        workspaces: record
+            .iter()
+            .map(|(ws_id, apps)| WorkspaceStats {
+                workspace_id: *ws_id,
+                apps: apps
+                    .iter()
+                    .map(|(app, duration)| AppStats {
+                        app: app.clone(),
+                        seconds: duration.as_secs(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn load_day(date: &str) -> Option<DayRecord> {
+    let content = fs::read_to_string(get_day_path(date)).ok()?;
+    let stats: DayStats = match serde_json::from_str(&content) {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("The stats of {} are invalid: {}", date, e);
+            return None;
+        }
+    };
+
+    Some(
+        stats
+            .workspaces
+            .into_iter()
+            .map(|ws| {
+                (
+                    ws.workspace_id,
+                    ws.apps
+                        .into_iter()
+                        .map(|app| (app.app, Duration::from_secs(app.seconds)))
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn save_day(stats: &DayStats) -> io::Result<()> {
+    let path = get_day_path(&stats.date);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_string_pretty(stats)?)
+}
@@ -0,0 +1,99 @@
+use crate::platform::{Area, Position, Size};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl std::fmt::Display for Edge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        use Edge::*;
+        write!(
+            f,
+            "{}",
+            match self {
+                Left => "Left",
+                Right => "Right",
+                Top => "Top",
+                Bottom => "Bottom",
+            }
+        )
+    }
+}
+
+impl std::str::FromStr for Edge {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Edge::*;
+        Ok(match s.to_ascii_uppercase().as_str() {
+            "LEFT" => Left,
+            "RIGHT" => Right,
+            "TOP" => Top,
+            "BOTTOM" => Bottom,
+            edge => return Err(format!("Unknown edge '{}'", edge)),
+        })
+    }
+}
+
+/// The amount of pixels that are reserved at each edge of a display (e.g. for a taskbar or a dock)
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Struts {
+    pub left: usize,
+    pub right: usize,
+    pub top: usize,
+    pub bottom: usize,
+}
+
+impl Struts {
+    /// Calculates the struts that are needed to shrink `outer` to `inner`
+    pub fn between(outer: Area, inner: Area) -> Self {
+        let outer_right = outer.pos.x + outer.size.width as isize;
+        let outer_bottom = outer.pos.y + outer.size.height as isize;
+        let inner_right = inner.pos.x + inner.size.width as isize;
+        let inner_bottom = inner.pos.y + inner.size.height as isize;
+
+        Self {
+            left: (inner.pos.x - outer.pos.x).max(0) as usize,
+            right: (outer_right - inner_right).max(0) as usize,
+            top: (inner.pos.y - outer.pos.y).max(0) as usize,
+            bottom: (outer_bottom - inner_bottom).max(0) as usize,
+        }
+    }
+
+    pub fn set(&mut self, edge: Edge, amount: usize) {
+        match edge {
+            Edge::Left => self.left = amount,
+            Edge::Right => self.right = amount,
+            Edge::Top => self.top = amount,
+            Edge::Bottom => self.bottom = amount,
+        }
+    }
+
+    /// Removes the reservations of `other`, which is used to give back the space of a hidden
+    /// taskbar.
+    pub fn without(self, other: Struts) -> Self {
+        Self {
+            left: self.left.saturating_sub(other.left),
+            right: self.right.saturating_sub(other.right),
+            top: self.top.saturating_sub(other.top),
+            bottom: self.bottom.saturating_sub(other.bottom),
+        }
+    }
+
+    /// Shrinks the area by the reserved amounts
+    pub fn apply(&self, area: Area) -> Area {
+        Area::new(
+            Size::new(
+                area.size.width.saturating_sub(self.left + self.right),
+                area.size.height.saturating_sub(self.top + self.bottom),
+            ),
+            Position::new(
+                area.pos.x + self.left as isize,
+                area.pos.y + self.top as isize,
+            ),
+        )
+    }
+}
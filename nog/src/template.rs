@@ -0,0 +1,149 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    launch::{self, LaunchCommand, LaunchOptions},
+    platform::{NativeWindow, WindowId},
+    rule::WindowMatcher,
+    session,
+    workspace::WorkspaceId,
+};
+
+/// Window handles never have the highest bit set, which is why it is used to mark the window ids
+/// of placeholders.
+const PLACEHOLDER_BIT: usize = 1 << (std::mem::size_of::<usize>() * 8 - 1);
+
+static NEXT_PLACEHOLDER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a window id that doesn't belong to any window. It reserves a slot in the layout until
+/// the window of a template slot shows up.
+pub fn create_placeholder() -> WindowId {
+    WindowId(PLACEHOLDER_BIT | NEXT_PLACEHOLDER.fetch_add(1, Ordering::Relaxed))
+}
+
+pub fn is_placeholder(id: WindowId) -> bool {
+    id.0 & PLACEHOLDER_BIT != 0
+}
+
+#[derive(Debug, Clone)]
+pub struct TemplateSlot {
+    pub cmd: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    /// Used for programs whose windows don't belong to the launched process (e.g. programs that
+    /// only allow a single instance)
+    pub matcher: Option<WindowMatcher>,
+}
+
+impl TemplateSlot {
+    pub fn launch_command(&self) -> LaunchCommand {
+        LaunchCommand {
+            cmd: self.cmd.clone(),
+            args: self.args.clone(),
+        }
+    }
+
+    pub fn launch_options(&self) -> LaunchOptions {
+        LaunchOptions {
+            args: self.args.clone(),
+            cwd: self.cwd.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceTemplate {
+    /// The layout the workspace switches to, as long as it doesn't contain any windows yet
+    pub layout: Option<String>,
+    pub slots: Vec<TemplateSlot>,
+}
+
+/// A slot of an applied template or a loaded session whose window didn't show up yet
+#[derive(Debug, Clone)]
+pub struct PendingSlot {
+    pub ws_id: WorkspaceId,
+    pub placeholder: WindowId,
+    /// The process that got launched for the slot, if nog knows it
+    pub pid: Option<u32>,
+    pub matcher: Option<WindowMatcher>,
+    /// How the window of the slot gets launched, if nog knows it
+    pub command: Option<LaunchCommand>,
+    /// The session the slot belongs to, `None` for the slots of templates
+    pub session: Option<String>,
+    /// Describes the missing window to the user
+    pub description: String,
+    launched_at: Instant,
+    timeout: Duration,
+}
+
+impl PendingSlot {
+    pub fn new(
+        ws_id: WorkspaceId,
+        placeholder: WindowId,
+        pid: Option<u32>,
+        matcher: Option<WindowMatcher>,
+        command: Option<LaunchCommand>,
+    ) -> Self {
+        Self {
+            ws_id,
+            placeholder,
+            pid,
+            matcher,
+            description: command
+                .as_ref()
+                .map(|command| command.cmd.clone())
+                .unwrap_or_default(),
+            command,
+            session: None,
+            launched_at: Instant::now(),
+            timeout: launch::LAUNCH_TIMEOUT,
+        }
+    }
+
+    /// Marks the slot as part of the session, which waits longer for its window (see
+    /// `session::SLOT_TIMEOUT`)
+    pub fn session(mut self, name: &str, description: String) -> Self {
+        self.session = Some(name.to_string());
+        self.description = description;
+        self.timeout = session::SLOT_TIMEOUT;
+        self
+    }
+}
+
+/// Removes and returns the slots whose window didn't show up in time, so that their placeholders
+/// can be removed from the layout.
+pub fn take_expired_slots(slots: &mut Vec<PendingSlot>) -> Vec<PendingSlot> {
+    let (expired, pending) = slots
+        .drain(..)
+        .partition(|slot| slot.launched_at.elapsed() >= slot.timeout);
+
+    *slots = pending;
+
+    expired
+}
+
+/// Removes and returns the slot the window belongs to. A slot with a matcher only accepts
+/// windows that match, otherwise the window has to belong to the launched process or one of its
+/// children.
+pub fn take_pending_slot(
+    slots: &mut Vec<PendingSlot>,
+    win: &impl NativeWindow,
+) -> Option<PendingSlot> {
+    if slots.is_empty() {
+        return None;
+    }
+
+    let pid = win.get_process_id();
+    let parent_ids = win.get_parent_process_ids();
+    let idx = slots.iter().position(|slot| match &slot.matcher {
+        Some(matcher) => matcher.matches(win),
+        None => slot
+            .pid
+            .map_or(false, |id| id == pid || parent_ids.contains(&id)),
+    })?;
+
+    Some(slots.remove(idx))
+}
@@ -1,12 +1,66 @@
-use crate::platform::NativeWindow;
+use std::{collections::HashMap, sync::Mutex};
+
+use lazy_static::lazy_static;
+
+use crate::platform::{NativeWindow, WindowId};
 
 mod win;
 
-#[derive(Debug, Clone)]
+lazy_static! {
+    /// The events that nog caused itself, in the order they are expected to arrive
+    static ref EXPECTED_EVENTS: Mutex<HashMap<WindowId, Vec<WindowEventKind>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Has to be called before nog minimizes or restores a window, so that the resulting event
+/// doesn't get mistaken for something the user did. Only call this if the window actually
+/// changes its state, since an event that never arrives would swallow the next one of the user.
+pub fn expect_event(id: WindowId, kind: WindowEventKind) {
+    EXPECTED_EVENTS
+        .lock()
+        .unwrap()
+        .entry(id)
+        .or_default()
+        .push(kind);
+}
+
+/// Returns whether nog caused the event itself and consumes the expectation. Expectations that
+/// are older than the matching one get dropped, since their events got lost.
+pub fn is_expected(id: WindowId, kind: &WindowEventKind) -> bool {
+    let mut windows = EXPECTED_EVENTS.lock().unwrap();
+
+    let expected = match windows.get_mut(&id) {
+        Some(expected) => expected,
+        None => return false,
+    };
+
+    let found = match expected.iter().position(|k| k == kind) {
+        Some(idx) => {
+            expected.drain(..=idx);
+            true
+        }
+        None => false,
+    };
+
+    if expected.is_empty() {
+        windows.remove(&id);
+    }
+
+    found
+}
+
+/// Forgets the expected events of a window that doesn't exist anymore
+pub fn forget_expected_events(id: WindowId) {
+    EXPECTED_EVENTS.lock().unwrap().remove(&id);
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum WindowEventKind {
     Created,
     Deleted,
     Minimized,
+    /// A minimized window got restored
+    Restored,
     FocusChanged,
 }
 
@@ -17,3 +71,72 @@ pub struct WindowEvent<TWin: NativeWindow> {
 }
 
 pub struct WindowEventLoop;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The registry is global, which is why every test uses its own window ids
+
+    #[test]
+    fn unexpected_event() {
+        let id = WindowId(1001);
+
+        assert!(!is_expected(id, &WindowEventKind::Minimized));
+    }
+
+    #[test]
+    fn expectation_gets_consumed() {
+        let id = WindowId(1002);
+
+        expect_event(id, WindowEventKind::Minimized);
+
+        assert!(is_expected(id, &WindowEventKind::Minimized));
+        assert!(!is_expected(id, &WindowEventKind::Minimized));
+    }
+
+    #[test]
+    fn other_kind_is_not_expected() {
+        let id = WindowId(1003);
+
+        expect_event(id, WindowEventKind::Minimized);
+
+        assert!(!is_expected(id, &WindowEventKind::Restored));
+        assert!(is_expected(id, &WindowEventKind::Minimized));
+    }
+
+    #[test]
+    fn lost_events_get_dropped() {
+        let id = WindowId(1004);
+
+        expect_event(id, WindowEventKind::Minimized);
+        expect_event(id, WindowEventKind::Restored);
+        expect_event(id, WindowEventKind::Minimized);
+
+        // The first minimize event got lost
+        assert!(is_expected(id, &WindowEventKind::Restored));
+        assert!(is_expected(id, &WindowEventKind::Minimized));
+        assert!(!is_expected(id, &WindowEventKind::Minimized));
+    }
+
+    #[test]
+    fn windows_are_independent() {
+        let id = WindowId(1005);
+        let other_id = WindowId(1006);
+
+        expect_event(id, WindowEventKind::Minimized);
+
+        assert!(!is_expected(other_id, &WindowEventKind::Minimized));
+        assert!(is_expected(id, &WindowEventKind::Minimized));
+    }
+
+    #[test]
+    fn forgotten_window() {
+        let id = WindowId(1007);
+
+        expect_event(id, WindowEventKind::Minimized);
+        forget_expected_events(id);
+
+        assert!(!is_expected(id, &WindowEventKind::Minimized));
+    }
+}
@@ -114,6 +114,7 @@ impl EventLoop for WindowEventLoop {
                 WinApiWindowEventKind::Minimize | WinApiWindowEventKind::Hide => {
                     Some(WindowEventKind::Minimized)
                 }
+                WinApiWindowEventKind::Unminimize => Some(WindowEventKind::Restored),
                 _ => None,
             };
 
@@ -1,14 +1,22 @@
-use std::{collections::HashMap, mem};
+use std::{
+    collections::HashMap,
+    mem,
+    time::{Duration, Instant},
+};
 
 use log::info;
 use mlua::FromLua;
 
 use crate::{
     cleanup::{WindowCleanup, WorkspaceCleanup},
-    config::Config,
+    config::{Config, DisplayConfigView},
     direction::Direction,
+    graph::Graph,
+    layout_preset::LayoutPreset,
     lua::{self, graph_proxy::GraphProxy, LuaEvent, LuaRuntime},
     platform::{Area, NativeWindow, Window, WindowId},
+    rule::LayoutSlot,
+    window_event_loop::{self, WindowEventKind},
     workspace::{Workspace, WorkspaceId},
 };
 
@@ -18,10 +26,91 @@ pub enum WindowManagerError {
 }
 pub type WindowManagerResult<T = ()> = Result<T, WindowManagerError>;
 
+/// The workspaces that got focused on a display, which can be walked like the history of a
+/// browser.
+#[derive(Debug, Default)]
+pub struct WorkspaceHistory {
+    entries: Vec<WorkspaceId>,
+    /// Index of the entry of the focused workspace
+    cursor: usize,
+}
+
+impl WorkspaceHistory {
+    /// Entries after the cursor get dropped, like when navigating to a new page in a browser.
+    pub fn push(&mut self, id: WorkspaceId) {
+        // This is the case when the workspace got changed by walking the history
+        if self.entries.get(self.cursor) == Some(&id) {
+            return;
+        }
+
+        self.entries.truncate(self.cursor + 1);
+        self.entries.push(id);
+        self.cursor = self.entries.len() - 1;
+    }
+
+    pub fn back(&mut self) -> Option<WorkspaceId> {
+        if self.cursor == 0 {
+            return None;
+        }
+
+        self.cursor -= 1;
+        self.entries.get(self.cursor).copied()
+    }
+
+    pub fn forward(&mut self) -> Option<WorkspaceId> {
+        if self.cursor + 1 >= self.entries.len() {
+            return None;
+        }
+
+        self.cursor += 1;
+        self.entries.get(self.cursor).copied()
+    }
+
+    /// Removes every entry of the workspace. This is only needed when the workspace moved to
+    /// another display, since workspaces that got removed can simply be created again.
+    pub fn remove(&mut self, id: WorkspaceId) {
+        let mut entries: Vec<WorkspaceId> = Vec::new();
+        let mut cursor = 0;
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if *entry != id && entries.last() != Some(entry) {
+                entries.push(*entry);
+            }
+
+            if idx == self.cursor {
+                cursor = entries.len().saturating_sub(1);
+            }
+        }
+
+        self.entries = entries;
+        self.cursor = cursor;
+    }
+}
+
+/// How long a cycle through the most recently used windows lasts without another step. Once
+/// it is over, the next step begins a new cycle with the current order.
+const MRU_CYCLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A snapshot of the most recently used windows, so that cycling through them doesn't change the
+/// order while cycling.
+#[derive(Debug)]
+pub struct MruCycle {
+    windows: Vec<WindowId>,
+    idx: usize,
+    last_step: Instant,
+}
+
 #[derive(Debug)]
 pub struct WindowManager {
     pub workspaces: Vec<Workspace>,
     pub focused_workspace_id: Option<WorkspaceId>,
+    /// The workspace that was focused before the current one
+    pub previous_workspace_id: Option<WorkspaceId>,
+    pub ws_history: WorkspaceHistory,
+    /// Floating windows that are visible on every workspace of the display. They don't belong to
+    /// any workspace, which is why hiding a workspace doesn't affect them.
+    pub sticky_windows: Vec<WindowId>,
+    pub mru_cycle: Option<MruCycle>,
     pub window_cleanup: HashMap<WindowId, WindowCleanup>,
     pub workspace_cleanup: HashMap<WorkspaceId, WorkspaceCleanup>,
 }
@@ -31,6 +120,10 @@ impl WindowManager {
         Self {
             workspaces: vec![],
             focused_workspace_id: None,
+            previous_workspace_id: None,
+            ws_history: WorkspaceHistory::default(),
+            sticky_windows: Vec::new(),
+            mru_cycle: None,
             window_cleanup: HashMap::new(),
             workspace_cleanup: HashMap::new(),
         }
@@ -50,37 +143,94 @@ impl WindowManager {
         }
     }
 
-    pub fn change_workspace(&mut self, rt: &LuaRuntime, id: WorkspaceId) {
+    pub fn change_workspace(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        id: WorkspaceId,
+    ) {
         match self.focused_workspace_id {
             Some(focused_workspace_id) if focused_workspace_id == id => return,
-            _ => match self.get_ws_by_id(id) {
-                Some(ws) => ws.unminimize(),
-                None => {
-                    self.workspaces.push(Workspace::new(id, "master_slave"));
-
-                    lua::emit_ws_created(&rt, LuaEvent::WsCreated { ws_id: id }).unwrap();
-                }
+            _ => match self.get_ws_by_id_mut(id) {
+                Some(ws) => ws.show_deferred(config),
+                None => self.create_workspace(rt, config, id),
             },
         };
 
         let old_ws_id = mem::replace(&mut self.focused_workspace_id, Some(id));
+
+        self.ws_history.push(id);
+
+        if old_ws_id.is_some() {
+            self.previous_workspace_id = old_ws_id;
+        }
+
         let old_ws = old_ws_id.and_then(|id| self.get_ws_by_id(id));
 
         if let Some(old_ws) = old_ws {
-            if old_ws.is_empty() {
+            if old_ws.is_empty() && !old_ws.persistent {
                 self.remove_workspace(old_ws_id.unwrap());
             } else {
-                let ws = self.get_ws_by_id(old_ws.id).unwrap();
-                ws.minimize();
+                let ws = self.get_ws_by_id_mut(old_ws.id).unwrap();
+                ws.hide(config.workspace_switch_strategy);
             }
         }
     }
 
-    pub fn focus_window(&mut self, rt: &LuaRuntime, id: WindowId) -> bool {
+    /// Creates the workspace without focusing it
+    pub fn create_workspace(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        id: WorkspaceId,
+    ) {
+        let mut ws = Workspace::new(id, &config.default_layout);
+
+        if let Some(definition) = config.workspaces.get(&id) {
+            ws.apply_definition(definition);
+        }
+
+        self.workspaces.push(ws);
+        self.sort_workspaces(config);
+
+        lua::emit_ws_created(&rt, LuaEvent::WsCreated { ws_id: id }).unwrap();
+    }
+
+    /// Makes sure that the workspace exists, so that windows can be managed into it. A workspace
+    /// that has to be created stays in the background, unless the display has no focused
+    /// workspace yet.
+    pub fn ensure_workspace(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        id: WorkspaceId,
+    ) {
+        if self.focused_workspace_id.is_none() {
+            self.change_workspace(rt, config, id);
+        } else if self.get_ws_by_id(id).is_none() {
+            self.create_workspace(rt, config, id);
+            self.get_ws_by_id_mut(id)
+                .unwrap()
+                .hide(config.workspace_switch_strategy);
+        }
+    }
+
+    pub fn sort_workspaces(&mut self, config: &Config) {
+        self.workspaces
+            .sort_by_key(|ws| config.get_ws_order_key(ws.id));
+    }
+
+    pub fn focus_window(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        id: WindowId,
+    ) -> bool {
         for ws in self.workspaces.iter_mut() {
             if ws.focus_window(id).is_ok() {
+                ws.update_auto_name(config);
                 let id = ws.id;
-                self.change_workspace(rt, id);
+                self.change_workspace(rt, config, id);
                 return true;
             }
         }
@@ -88,6 +238,155 @@ impl WindowManager {
         false
     }
 
+    /// The windows of every workspace ordered by when they got focused, beginning with the most
+    /// recent one
+    pub fn get_mru_windows(&self) -> Vec<WindowId> {
+        let mut history: Vec<(WindowId, Instant)> = self
+            .workspaces
+            .iter()
+            .flat_map(|ws| ws.focus_history.iter().copied())
+            .collect();
+
+        history.sort_by(|(_, a), (_, b)| b.cmp(a));
+        history.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Returns the next window of the current cycle through the most recently used windows,
+    /// which begins with the focused window.
+    pub fn cycle_mru(&mut self, reverse: bool) -> Option<WindowId> {
+        let now = Instant::now();
+        let expired = self
+            .mru_cycle
+            .as_ref()
+            .map(|cycle| now.duration_since(cycle.last_step) > MRU_CYCLE_TIMEOUT)
+            .unwrap_or(true);
+
+        if expired {
+            let windows = self
+                .get_mru_windows()
+                .into_iter()
+                .filter(|id| self.has_window(*id))
+                .collect();
+
+            self.mru_cycle = Some(MruCycle {
+                windows,
+                idx: 0,
+                last_step: now,
+            });
+        }
+
+        let cycle = self.mru_cycle.as_mut().unwrap();
+        let len = cycle.windows.len();
+
+        if len < 2 {
+            return None;
+        }
+
+        cycle.idx = if reverse {
+            (cycle.idx + len - 1) % len
+        } else {
+            (cycle.idx + 1) % len
+        };
+        cycle.last_step = now;
+
+        cycle.windows.get(cycle.idx).copied()
+    }
+
+    /// Returns the workspace a new tiled window has to go to, in case the workspace already has
+    /// the maximum amount of tiled windows. This is the next workspace that has room for the
+    /// window, skipping the workspaces in `taken`, which belong to other displays.
+    pub fn get_overflow_ws_id(
+        &self,
+        config: &DisplayConfigView,
+        ws_id: WorkspaceId,
+        taken: &[WorkspaceId],
+    ) -> Option<WorkspaceId> {
+        let is_full = |id: WorkspaceId| match self.get_ws_by_id(id) {
+            Some(ws) => config
+                .get_max_windows(id, &ws.layout_name)
+                .map(|max| ws.get_windows_in_order().len() >= max)
+                .unwrap_or(false),
+            None => false,
+        };
+
+        if !is_full(ws_id) {
+            return None;
+        }
+
+        // Terminates, since there is always a workspace id that isn't used yet
+        (ws_id.0 + 1..)
+            .map(WorkspaceId)
+            .find(|id| !taken.contains(id) && !is_full(*id))
+    }
+
+    /// Returns the window that comes after the focused window of the workspace in depth-first
+    /// order. The order wraps around, either within the workspace or, when `across` is true,
+    /// through the workspaces of the display.
+    pub fn get_window_in_order(
+        &self,
+        ws_id: WorkspaceId,
+        across: bool,
+        reverse: bool,
+    ) -> Option<WindowId> {
+        let ws_idx = self.workspaces.iter().position(|ws| ws.id == ws_id)?;
+        let ws = &self.workspaces[ws_idx];
+        let windows = ws.get_windows_in_order();
+        let next_idx = ws
+            .get_focused_win()
+            .and_then(|win| windows.iter().position(|id| *id == win.get_id()))
+            .and_then(|idx| {
+                if reverse {
+                    idx.checked_sub(1)
+                } else {
+                    Some(idx + 1).filter(|idx| *idx < windows.len())
+                }
+            });
+
+        if let Some(idx) = next_idx {
+            return windows.get(idx).copied();
+        }
+
+        if across {
+            let count = self.workspaces.len();
+
+            for offset in 1..count {
+                let idx = if reverse {
+                    (ws_idx + count - offset) % count
+                } else {
+                    (ws_idx + offset) % count
+                };
+                let other = self.workspaces[idx].get_windows_in_order();
+                let win_id = if reverse { other.last() } else { other.first() };
+
+                if let Some(win_id) = win_id {
+                    return Some(*win_id);
+                }
+            }
+        }
+
+        if reverse {
+            windows.last().copied()
+        } else {
+            windows.first().copied()
+        }
+    }
+
+    /// Focuses the most recently focused window of the workspace, which is used when the
+    /// focused window leaves the layout.
+    fn restore_focus(&mut self, ws_id: WorkspaceId) {
+        let is_focused_ws = self.focused_workspace_id == Some(ws_id);
+
+        if let Some(ws) = self.get_ws_by_id_mut(ws_id) {
+            if let Some(id) = ws.get_last_focused_win(None) {
+                ws.focus_window(id).ok();
+
+                if is_focused_ws {
+                    Window::new(id).focus();
+                }
+            }
+        }
+    }
+
     pub fn get_focused_workspace(&self) -> &Workspace {
         let id = self.focused_workspace_id.unwrap();
         self.workspaces.iter().find(|ws| ws.id == id).unwrap()
@@ -102,20 +401,143 @@ impl WindowManager {
     }
 
     pub fn has_window(&self, id: WindowId) -> bool {
+        self.is_sticky(id)
+            || self
+                .workspaces
+                .iter()
+                .map(|ws| ws.has_window(id))
+                .any(|x| x)
+    }
+
+    /// Returns whether the window belongs to a workspace that isn't focused
+    pub fn is_in_background(&self, id: WindowId) -> bool {
         self.workspaces
             .iter()
-            .map(|ws| ws.has_window(id))
-            .any(|x| x)
+            .find(|ws| ws.has_window(id))
+            .map(|ws| Some(ws.id) != self.focused_workspace_id)
+            .unwrap_or(false)
+    }
+
+    pub fn mark_urgent(&mut self, id: WindowId) {
+        if let Some(ws) = self.workspaces.iter_mut().find(|ws| ws.has_window(id)) {
+            if !ws.is_win_urgent(id) {
+                ws.urgent_windows.push(id);
+            }
+        }
+    }
+
+    /// Hides a window of a workspace that isn't focused again, after it showed itself to steal
+    /// the focus.
+    pub fn push_to_background(&mut self, id: WindowId) {
+        if let Some(ws) = self.workspaces.iter_mut().find(|ws| ws.has_window(id)) {
+            if ws.is_hidden() {
+                ws.restore_window(id);
+            }
+        }
+    }
+
+    pub fn is_sticky(&self, id: WindowId) -> bool {
+        self.sticky_windows.contains(&id)
+    }
+
+    /// Removes the window from its workspace, which floats it in case it was part of the layout.
+    pub fn make_sticky(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+        win_id: WindowId,
+    ) -> WindowManagerResult {
+        if self.is_sticky(win_id) {
+            return Ok(());
+        }
+
+        let ws_id = self
+            .workspaces
+            .iter()
+            .find(|ws| ws.has_window(win_id))
+            .map(|ws| ws.id);
+
+        if let Some(ws_id) = ws_id {
+            let ws = self.get_ws_by_id_mut(ws_id).unwrap();
+
+            if ws.is_floating(win_id) {
+                ws.floating_windows.retain(|id| *id != win_id);
+            } else if ws.is_parked(win_id) {
+                ws.parked_windows.remove(&win_id);
+            } else {
+                self.organize(
+                    rt,
+                    config,
+                    Some(ws_id),
+                    area,
+                    String::from("unmanaged"),
+                    win_id,
+                )?;
+            }
+        }
+
+        self.sticky_windows.push(win_id);
+
+        Ok(())
+    }
+
+    /// The window stays floating in the focused workspace
+    pub fn unstick(&mut self, config: &DisplayConfigView, win_id: WindowId) {
+        if !self.is_sticky(win_id) {
+            return;
+        }
+
+        self.sticky_windows.retain(|id| *id != win_id);
+
+        if let Some(id) = self.focused_workspace_id {
+            self.float(config, Some(id), Window::new(win_id));
+        }
     }
 
     pub fn manage(
         &mut self,
         rt: &LuaRuntime,
-        config: &Config,
+        config: &DisplayConfigView,
         ws_id: Option<WorkspaceId>,
         area: Area,
         win: Window,
+        layout_slot: Option<LayoutSlot>,
     ) -> WindowManagerResult {
+        self.prepare_window(config, win);
+
+        // A rule that decides the slot of the window takes precedence over swallowing
+        if layout_slot.is_none() {
+            let ws_id = ws_id.unwrap_or_else(|| self.focused_workspace_id.unwrap());
+
+            if let Some(parent_id) = self.find_swallow_parent(config, ws_id, win) {
+                self.swallow(config, area, ws_id, parent_id, win);
+                return Ok(());
+            }
+        }
+
+        self.organize(
+            rt,
+            config,
+            ws_id,
+            area,
+            String::from("managed"),
+            (win.get_id(), layout_slot),
+        )?;
+
+        // A window that gets managed into a hidden workspace has to be hidden like its siblings
+        let ws_id = ws_id.unwrap_or_else(|| self.focused_workspace_id.unwrap());
+
+        if let Some(ws) = self.get_ws_by_id_mut(ws_id).filter(|ws| ws.is_hidden()) {
+            ws.restore_window(win.get_id());
+        }
+
+        Ok(())
+    }
+
+    /// Removes the decorations of the window and remembers how to undo the changes nog makes to
+    /// the window.
+    fn prepare_window(&mut self, config: &DisplayConfigView, win: Window) {
         let cleanup = self.window_cleanup.entry(win.get_id()).or_default();
 
         if win.is_maximized() {
@@ -139,21 +561,174 @@ impl WindowManager {
         if config.remove_decorations {
             cleanup.add_decorations = Some(win.remove_decorations());
         }
+    }
 
+    /// Adds a placeholder to the layout, which reserves a slot for a window that doesn't exist
+    /// yet.
+    pub fn add_placeholder(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        ws_id: WorkspaceId,
+        area: Area,
+        placeholder: WindowId,
+    ) -> WindowManagerResult {
         self.organize(
             rt,
             config,
-            ws_id,
+            Some(ws_id),
             area,
             String::from("managed"),
-            win.get_id(),
+            (placeholder, None::<LayoutSlot>),
         )
     }
 
+    pub fn remove_placeholder(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        ws_id: WorkspaceId,
+        area: Area,
+        placeholder: WindowId,
+    ) -> WindowManagerResult {
+        if self
+            .get_ws_by_id(ws_id)
+            .map_or(true, |ws| ws.graph.get_window_node(placeholder).is_none())
+        {
+            return Ok(());
+        }
+
+        self.organize(
+            rt,
+            config,
+            Some(ws_id),
+            area,
+            String::from("unmanaged"),
+            placeholder,
+        )
+    }
+
+    /// Puts the window into the slot of the placeholder. Like swallowing, this doesn't notify
+    /// the layout, since the structure of the graph doesn't change.
+    ///
+    /// Returns false if the placeholder doesn't exist anymore.
+    pub fn fill_placeholder(
+        &mut self,
+        config: &DisplayConfigView,
+        ws_id: WorkspaceId,
+        area: Area,
+        placeholder: WindowId,
+        win: Window,
+    ) -> bool {
+        let filled = self.get_ws_by_id_mut(ws_id).map_or(false, |ws| {
+            ws.graph.replace_window(placeholder, win.get_id()).is_ok()
+        });
+
+        if !filled {
+            return false;
+        }
+
+        self.prepare_window(config, win);
+
+        let ws = self.get_ws_by_id_mut(ws_id).unwrap();
+
+        ws.update_auto_name(config);
+
+        if ws.is_hidden() {
+            ws.restore_window(win.get_id());
+        }
+
+        ws.render_or_defer(config, area);
+
+        true
+    }
+
+    /// Returns the window of a swallow parent in the workspace whose process (indirectly) started
+    /// the process of the window. The closest ancestor wins if there are multiple candidates.
+    fn find_swallow_parent(
+        &self,
+        config: &DisplayConfigView,
+        ws_id: WorkspaceId,
+        win: Window,
+    ) -> Option<WindowId> {
+        if config.swallow_parents.is_empty() {
+            return None;
+        }
+
+        let ws = self.get_ws_by_id(ws_id)?;
+        let candidates: Vec<Window> = ws
+            .graph
+            .nodes
+            .values()
+            .filter_map(|n| n.try_get_window_id())
+            .map(Window::new)
+            .filter(|w| config.is_swallow_parent(&w.get_process_name()))
+            .collect();
+
+        if candidates.is_empty() {
+            return None;
+        }
+
+        win.get_parent_process_ids()
+            .into_iter()
+            .find_map(|pid| candidates.iter().find(|w| w.get_process_id() == pid))
+            .map(|w| w.get_id())
+    }
+
+    /// Puts the window into the slot of the parent, which stays hidden until the window gets
+    /// unmanaged. The layout doesn't get notified, since the structure of the graph doesn't
+    /// change.
+    fn swallow(
+        &mut self,
+        config: &DisplayConfigView,
+        area: Area,
+        ws_id: WorkspaceId,
+        parent_id: WindowId,
+        win: Window,
+    ) {
+        let ws = self.get_ws_by_id_mut(ws_id).unwrap();
+
+        if ws.graph.replace_window(parent_id, win.get_id()).is_err() {
+            return;
+        }
+
+        info!("'{}' swallowed {}", win.get_title(), parent_id);
+
+        ws.swallowed_windows.insert(win.get_id(), parent_id);
+        ws.update_auto_name(config);
+
+        // Hiding a window is reported as minimizing it, which mustn't unmanage or park the parent
+        let parent = Window::new(parent_id);
+        if parent.is_visible() {
+            window_event_loop::expect_event(parent_id, WindowEventKind::Minimized);
+        }
+        parent.hide();
+
+        if ws.is_hidden() {
+            ws.restore_window(win.get_id());
+        }
+
+        ws.render_or_defer(config, area);
+    }
+
+    /// Manages the window without adding it to the layout of the workspace
+    pub fn float(&mut self, config: &DisplayConfigView, ws_id: Option<WorkspaceId>, win: Window) {
+        let ws_id = ws_id.unwrap_or_else(|| self.focused_workspace_id.unwrap());
+
+        if let Some(ws) = self.get_ws_by_id_mut(ws_id) {
+            ws.floating_windows.push(win.get_id());
+            ws.update_auto_name(config);
+
+            if ws.is_hidden() {
+                ws.restore_window(win.get_id());
+            }
+        }
+    }
+
     pub fn swap_in_direction(
         &mut self,
         rt: &LuaRuntime,
-        config: &Config,
+        config: &DisplayConfigView,
         area: Area,
         maybe_id: Option<WindowId>,
         dir: Direction,
@@ -171,15 +746,87 @@ impl WindowManager {
         Ok(())
     }
 
+    pub fn update_ws_names(&mut self, config: &DisplayConfigView) {
+        for ws in self.workspaces.iter_mut() {
+            ws.update_auto_name(config);
+        }
+    }
+
     /// Only renders the visible workspace
-    pub fn render(&self, config: &Config, area: Area) {
-        self.get_focused_workspace().render(config, area);
+    pub fn render(&self, config: &DisplayConfigView, area: Area) {
+        if let Some(ws) = self
+            .focused_workspace_id
+            .and_then(|id| self.get_ws_by_id(id))
+        {
+            ws.render(config, area);
+        }
+    }
+
+    /// Removes the workspace together with the cleanup of its windows, so that it can be
+    /// inserted into the window manager of another display.
+    ///
+    /// If the workspace was focused, the next workspace gets focused instead. In case there is
+    /// no other workspace, a new one with the id `fallback_id` gets created.
+    pub fn take_workspace(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        id: WorkspaceId,
+        fallback_id: WorkspaceId,
+    ) -> Option<(Workspace, HashMap<WindowId, WindowCleanup>)> {
+        let idx = self.workspaces.iter().position(|ws| ws.id == id)?;
+        let ws = self.workspaces.remove(idx);
+
+        self.ws_history.remove(id);
+
+        if self.previous_workspace_id == Some(id) {
+            self.previous_workspace_id = None;
+        }
+
+        let window_cleanup = ws
+            .windows()
+            .filter_map(|win_id| {
+                self.window_cleanup
+                    .remove(&win_id)
+                    .map(|cleanup| (win_id, cleanup))
+            })
+            .collect();
+
+        if self.focused_workspace_id == Some(id) {
+            self.focused_workspace_id = None;
+
+            let next_id = self
+                .workspaces
+                .first()
+                .map(|ws| ws.id)
+                .unwrap_or(fallback_id);
+
+            self.change_workspace(rt, config, next_id);
+        }
+
+        Some((ws, window_cleanup))
+    }
+
+    /// Inserts a workspace that was taken from another window manager and focuses it.
+    pub fn insert_workspace(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        ws: Workspace,
+        window_cleanup: HashMap<WindowId, WindowCleanup>,
+    ) {
+        let id = ws.id;
+
+        self.workspaces.push(ws);
+        self.sort_workspaces(config);
+        self.window_cleanup.extend(window_cleanup);
+        self.change_workspace(rt, config, id);
     }
 
     pub fn organize<TArgs: mlua::ToLuaMulti<'static>>(
         &mut self,
         rt: &LuaRuntime,
-        config: &Config,
+        config: &DisplayConfigView,
         ws_id: Option<WorkspaceId>,
         area: Area,
         reason: String,
@@ -207,9 +854,173 @@ impl WindowManager {
 
         if workspace.graph.dirty {
             info!("Have to rerender!");
-            workspace.render(config, area);
             println!("{}", &workspace.graph);
-            workspace.graph.dirty = false;
+            workspace.render_or_defer(config, area);
+        }
+
+        workspace.update_auto_name(config);
+
+        Ok(())
+    }
+
+    /// Changes the layout of the workspace and throws away the state of the previous layout
+    /// function, which doesn't match the graph anymore.
+    pub fn set_layout(
+        &mut self,
+        rt: &LuaRuntime,
+        ws_id: WorkspaceId,
+        layout_name: &str,
+    ) -> WindowManagerResult {
+        if let Some(ws) = self.get_ws_by_id_mut(ws_id) {
+            ws.layout_name = layout_name.to_string();
+        }
+
+        self.reset_layout(rt, ws_id)
+    }
+
+    /// Throws away the state of the layout function of the workspace
+    fn reset_layout(&self, rt: &LuaRuntime, ws_id: WorkspaceId) -> WindowManagerResult {
+        rt.lua
+            .load(&format!("nog.__reset_layout({})", ws_id.0))
+            .exec()
+            .map_err(|e| WindowManagerError::LayoutFunctionError(e.to_string()))
+    }
+
+    /// Rebuilds the graph of the workspace with the shape of the preset, putting the most
+    /// recently focused windows into the first slots. The windows that don't fit into the
+    /// preset get added by the manual layout, which the workspace uses afterwards, since no
+    /// other layout knows about the structure of the preset.
+    pub fn apply_layout_preset(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+        ws_id: WorkspaceId,
+        preset: &LayoutPreset,
+    ) -> WindowManagerResult {
+        let ws = match self.get_ws_by_id_mut(ws_id) {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        let focused_id = ws.get_focused_win().map(|win| win.get_id());
+        let mut windows = ws.get_windows_by_mru().into_iter().peekable();
+
+        ws.graph = preset.build_graph(&mut windows);
+
+        let remaining: Vec<WindowId> = windows.collect();
+
+        self.set_layout(rt, ws_id, "manual")?;
+
+        for id in remaining {
+            self.organize(
+                rt,
+                config,
+                Some(ws_id),
+                area,
+                String::from("managed"),
+                (id, None::<LayoutSlot>),
+            )?;
+        }
+
+        let ws = self.get_ws_by_id_mut(ws_id).unwrap();
+
+        if let Some(id) = focused_id {
+            let _ = ws.focus_window(id);
+        }
+
+        if !ws.is_hidden() {
+            ws.render(config, area);
+            ws.graph.dirty = false;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a window that got minimized by the user from the layout, while remembering its
+    /// slot so that `unpark` can put it back.
+    pub fn park(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+        win_id: WindowId,
+    ) -> WindowManagerResult {
+        let (ws_id, slot) = match self.workspaces.iter().find_map(|ws| {
+            let node_id = ws.graph.get_window_node(win_id)?;
+            let slot = ws
+                .graph
+                .get_window_nodes_in_order()
+                .iter()
+                .position(|id| *id == node_id)?;
+
+            Some((ws.id, slot))
+        }) {
+            Some(x) => x,
+            // Floating windows don't have a slot in the layout
+            None => return Ok(()),
+        };
+
+        let was_focused = self
+            .get_ws_by_id(ws_id)
+            .and_then(|ws| ws.get_focused_win())
+            .map(|win| win.get_id())
+            == Some(win_id);
+
+        self.organize(
+            rt,
+            config,
+            Some(ws_id),
+            area,
+            String::from("minimized"),
+            win_id,
+        )?;
+
+        if let Some(ws) = self.get_ws_by_id_mut(ws_id) {
+            ws.parked_windows.insert(win_id, slot);
+            ws.forget_focus(win_id);
+        }
+
+        if was_focused {
+            self.restore_focus(ws_id);
+        }
+
+        Ok(())
+    }
+
+    /// Puts a parked window back into the slot it had before it got minimized. The workspace of
+    /// the window gets focused, since the user wants to see the window.
+    pub fn unpark(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+        win_id: WindowId,
+    ) -> WindowManagerResult {
+        let (ws_id, slot) = match self
+            .workspaces
+            .iter_mut()
+            .find_map(|ws| ws.parked_windows.remove(&win_id).map(|slot| (ws.id, slot)))
+        {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        self.change_workspace(rt, config, ws_id);
+
+        // The layout gets the slot as a lua index, so that it can put the window back into the
+        // right place without losing track of its own state
+        self.organize(
+            rt,
+            config,
+            Some(ws_id),
+            area,
+            String::from("managed"),
+            (win_id, LayoutSlot::Position(slot + 1)),
+        )?;
+
+        if let Some(ws) = self.get_ws_by_id_mut(ws_id) {
+            ws.focus_window(win_id).ok();
         }
 
         Ok(())
@@ -218,29 +1029,328 @@ impl WindowManager {
     pub fn unmanage(
         &mut self,
         rt: &LuaRuntime,
-        config: &Config,
+        config: &DisplayConfigView,
         area: Area,
         win_id: WindowId,
     ) -> WindowManagerResult {
-        if let Some(cleanup) = self.window_cleanup.get(&win_id) {
+        let ws_id = self
+            .workspaces
+            .iter()
+            .find(|ws| ws.has_window(win_id))
+            .map(|ws| ws.id);
+
+        let result = self.remove_window(rt, config, area, win_id);
+
+        if let Some(ws) = ws_id.and_then(|id| self.get_ws_by_id_mut(id)) {
+            ws.update_auto_name(config);
+        }
+
+        result
+    }
+
+    fn remove_window(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+        win_id: WindowId,
+    ) -> WindowManagerResult {
+        for ws in self.workspaces.iter_mut() {
+            ws.urgent_windows.retain(|id| *id != win_id);
+        }
+
+        // A window that swallowed another window gives its slot back
+        if let Some(ws) = self
+            .workspaces
+            .iter_mut()
+            .find(|ws| ws.swallowed_windows.contains_key(&win_id))
+        {
+            let swallowed_id = ws.swallowed_windows.remove(&win_id).unwrap();
+            ws.restore_window(swallowed_id);
+
+            if ws.graph.replace_window(win_id, swallowed_id).is_ok() {
+                ws.forget_focus(win_id);
+                ws.render(config, area);
+                ws.graph.dirty = false;
+                self.reset_window(win_id);
+                return Ok(());
+            }
+
+            // The window isn't part of the layout anymore (e.g. because it got parked), which is
+            // why the swallowed window needs a new slot.
+            let ws_id = ws.id;
+            self.organize(
+                rt,
+                config,
+                Some(ws_id),
+                area,
+                String::from("managed"),
+                (swallowed_id, None::<LayoutSlot>),
+            )?;
+        }
+
+        if let Some(ws) = self
+            .workspaces
+            .iter_mut()
+            .find(|ws| ws.is_swallowed(win_id))
+        {
+            ws.swallowed_windows.retain(|_, id| *id != win_id);
+            self.window_cleanup.remove(&win_id);
+            return Ok(());
+        }
+
+        // Floating windows are not part of the layout, so there is nothing to organize
+        if let Some(ws) = self.workspaces.iter_mut().find(|ws| ws.is_floating(win_id)) {
+            ws.floating_windows.retain(|id| *id != win_id);
+            return Ok(());
+        }
+
+        // Neither sticky nor parked windows are part of the layout
+        if self.is_sticky(win_id) {
+            self.sticky_windows.retain(|id| *id != win_id);
+            self.reset_window(win_id);
+            return Ok(());
+        }
+
+        let is_parked = match self.workspaces.iter_mut().find(|ws| ws.is_parked(win_id)) {
+            Some(ws) => ws.parked_windows.remove(&win_id).is_some(),
+            None => false,
+        };
+
+        // Resetting the transform would move the minimized window, so only the decorations get
+        // added back
+        if is_parked {
+            if let Some(cleanup) = self.window_cleanup.remove(&win_id) {
+                if let Some(f) = cleanup.add_decorations {
+                    f();
+                }
+            }
+
+            return Ok(());
+        }
+
+        self.reset_window(win_id);
+
+        let ws = self.workspaces.iter().find(|ws| ws.has_window(win_id));
+        let ws_id = ws.map(|ws| ws.id);
+        let was_focused = ws
+            .and_then(|ws| ws.get_focused_win())
+            .map(|win| win.get_id())
+            == Some(win_id);
+
+        self.organize(rt, config, ws_id, area, String::from("unmanaged"), win_id)?;
+
+        for ws in self.workspaces.iter_mut() {
+            ws.forget_focus(win_id);
+        }
+
+        if let (Some(ws_id), true) = (ws_id, was_focused) {
+            self.restore_focus(ws_id);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the decorations back and restores the size and position the window had before it
+    /// got managed
+    fn reset_window(&mut self, win_id: WindowId) {
+        if let Some(cleanup) = self.window_cleanup.remove(&win_id) {
             info!("Doing cleanup for {}", win_id);
-            if let Some(f) = cleanup.add_decorations.as_ref() {
+            if let Some(f) = cleanup.add_decorations {
                 f();
             }
 
-            if let Some(f) = cleanup.reset_transform.as_ref() {
+            if let Some(f) = cleanup.reset_transform {
                 f();
             }
         }
+    }
+
+    /// Gives the windows back to the user without forgetting about them, so that `resume` can
+    /// put them back into their slots. The decorations and transforms get reset, while the
+    /// workspaces and their layouts stay intact.
+    pub fn suspend(&mut self) {
+        for ws in self.workspaces.iter_mut() {
+            ws.show();
+
+            let swallowed: Vec<WindowId> = ws.swallowed_windows.values().copied().collect();
+
+            for id in swallowed {
+                Window::new(id).show();
+            }
+        }
 
-        self.organize(rt, config, None, area, String::from("unmanaged"), win_id)?;
+        for cleanup in self.window_cleanup.values_mut() {
+            if let Some(f) = cleanup.add_decorations.take() {
+                f();
+            }
+            if let Some(f) = &cleanup.reset_transform {
+                f();
+            }
+        }
 
-        self.window_cleanup.remove(&win_id);
+        self.mru_cycle = None;
+    }
+
+    /// Reverts what `suspend` did. Windows that got closed in the meantime get unmanaged.
+    pub fn resume(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+    ) -> WindowManagerResult {
+        let closed: Vec<WindowId> = self
+            .workspaces
+            .iter()
+            .flat_map(|ws| ws.windows().collect::<Vec<_>>())
+            .chain(self.sticky_windows.iter().copied())
+            .filter(|id| !Window::new(*id).exists())
+            .collect();
+
+        for id in closed {
+            self.unmanage(rt, config, area, id)?;
+        }
+
+        if config.remove_decorations {
+            for (id, cleanup) in self.window_cleanup.iter_mut() {
+                cleanup.add_decorations = Some(Window::new(*id).remove_decorations());
+            }
+        }
+
+        for ws in self.workspaces.iter_mut() {
+            let swallowed: Vec<WindowId> = ws.swallowed_windows.values().copied().collect();
+
+            for id in swallowed {
+                let win = Window::new(id);
+                if win.is_visible() {
+                    window_event_loop::expect_event(id, WindowEventKind::Minimized);
+                }
+                win.hide();
+            }
+
+            if Some(ws.id) == self.focused_workspace_id {
+                ws.render(config, area);
+                ws.graph.dirty = false;
+            } else {
+                ws.hide(config.workspace_switch_strategy);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the workspaces with the ones of a session. The manual layout doesn't have any
+    /// state, which is why its graphs are kept as they got saved. Every other layout only knows
+    /// about the nodes it created itself, so the windows get organized again in the order they
+    /// had.
+    pub fn load_workspaces(
+        &mut self,
+        rt: &LuaRuntime,
+        config: &DisplayConfigView,
+        area: Area,
+        workspaces: Vec<Workspace>,
+        sticky_windows: Vec<WindowId>,
+        focused_ws_id: Option<WorkspaceId>,
+    ) -> WindowManagerResult {
+        let old_ws_ids: Vec<WorkspaceId> = self.workspaces.iter().map(|ws| ws.id).collect();
+
+        self.cleanup();
+
+        for id in old_ws_ids {
+            self.reset_layout(rt, id)?;
+        }
+
+        self.workspaces = workspaces;
+        self.sticky_windows = sticky_windows;
+        self.sort_workspaces(config);
+
+        let windows: Vec<WindowId> = self
+            .workspaces
+            .iter()
+            .flat_map(|ws| ws.windows().collect::<Vec<_>>())
+            .chain(self.sticky_windows.iter().copied())
+            .collect();
+
+        for id in windows {
+            self.prepare_window(config, Window::new(id));
+        }
+
+        let ws_ids: Vec<WorkspaceId> = self.workspaces.iter().map(|ws| ws.id).collect();
+
+        for ws_id in ws_ids {
+            self.reset_layout(rt, ws_id)?;
+
+            let ws = self.get_ws_by_id_mut(ws_id).unwrap();
+
+            if ws.layout_name != "manual" {
+                let focused_id = ws.get_focused_win().map(|win| win.get_id());
+                // Placeholders have to keep their slot as well
+                let windows: Vec<WindowId> = ws
+                    .graph
+                    .get_window_nodes_in_order()
+                    .into_iter()
+                    .filter_map(|id| ws.graph.get_node(id))
+                    .filter_map(|node| node.try_get_window_id())
+                    .collect();
+
+                ws.graph = Graph::new();
+
+                for id in windows {
+                    self.organize(
+                        rt,
+                        config,
+                        Some(ws_id),
+                        area,
+                        String::from("managed"),
+                        (id, None::<LayoutSlot>),
+                    )?;
+                }
+
+                let ws = self.get_ws_by_id_mut(ws_id).unwrap();
+
+                if let Some(id) = focused_id {
+                    let _ = ws.focus_window(id);
+                }
+            }
+
+            self.get_ws_by_id_mut(ws_id)
+                .unwrap()
+                .update_auto_name(config);
+
+            lua::emit_ws_created(&rt, LuaEvent::WsCreated { ws_id }).unwrap();
+        }
+
+        self.focused_workspace_id = focused_ws_id
+            .filter(|id| self.get_ws_by_id(*id).is_some())
+            .or_else(|| self.workspaces.first().map(|ws| ws.id));
+
+        if let Some(id) = self.focused_workspace_id {
+            self.ws_history.push(id);
+        }
+
+        for ws in self.workspaces.iter_mut() {
+            if Some(ws.id) == self.focused_workspace_id {
+                ws.render(config, area);
+                ws.graph.dirty = false;
+            } else {
+                ws.hide(config.workspace_switch_strategy);
+            }
+        }
 
         Ok(())
     }
 
     pub fn cleanup(&mut self) {
+        for ws in self.workspaces.iter_mut() {
+            ws.show();
+
+            let swallowed: Vec<WindowId> = ws.swallowed_windows.drain().map(|(_, id)| id).collect();
+
+            for id in swallowed {
+                ws.restore_window(id);
+            }
+        }
+
         for (_, v) in mem::take(&mut self.window_cleanup) {
             if let Some(f) = v.add_decorations {
                 f();
@@ -251,6 +1361,10 @@ impl WindowManager {
         }
 
         self.focused_workspace_id = None;
+        self.previous_workspace_id = None;
+        self.ws_history = WorkspaceHistory::default();
+        self.sticky_windows = vec![];
+        self.mru_cycle = None;
         self.workspaces = vec![];
     }
 }
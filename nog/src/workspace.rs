@@ -1,7 +1,21 @@
-use crate::config::Config;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::config::{
+    Config, DisplayConfigView, WorkspaceDefinition, WorkspaceNaming, WorkspaceSwitchStrategy,
+};
 use crate::direction::Direction;
 use crate::graph::{Graph, GraphNode, GraphNodeGroupKind, GraphNodeId};
-use crate::platform::{Area, NativeWindow, Window, WindowId};
+use crate::platform::{Area, NativeWindow, Position, Window, WindowId};
+use crate::template;
+use crate::window_event_loop::{self, WindowEventKind};
+
+/// Where windows get moved to by the offscreen strategy. This is far enough away from any
+/// display, but still a valid position for a window.
+const OFFSCREEN_POSITION: Position = Position {
+    x: -30000,
+    y: -30000,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum WorkspaceState {
@@ -9,17 +23,42 @@ pub enum WorkspaceState {
     Normal,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct WorkspaceId(pub usize);
 
 #[derive(Debug)]
 pub struct Workspace {
     pub id: WorkspaceId,
-    /// Initially this is set to the id of the workspace
+    /// Initially this is set to the id of the workspace, unless it gets named automatically
+    /// (see `WorkspaceNaming`)
     pub display_name: String,
     pub layout_name: String,
     pub state: WorkspaceState,
     pub graph: Graph,
+    /// Windows that belong to the workspace, but are not part of the layout
+    pub floating_windows: Vec<WindowId>,
+    /// A persistent workspace doesn't get removed when it is empty
+    pub persistent: bool,
+    /// Windows that got minimized by the user, together with the index of the slot they had in
+    /// the layout (see `Graph::get_window_nodes_in_order`)
+    pub parked_windows: HashMap<WindowId, usize>,
+    /// Windows that got swallowed, keyed by the window that took their slot in the layout
+    pub swallowed_windows: HashMap<WindowId, WindowId>,
+    /// The windows of the layout ordered by when they got focused, beginning with the most
+    /// recent one
+    pub focus_history: Vec<(WindowId, Instant)>,
+    /// Windows that tried to get focus while the workspace wasn't focused (see
+    /// `FocusStealingPolicy::Urgent`). A window stays urgent until it gets focused.
+    pub urgent_windows: Vec<WindowId>,
+    /// How the windows got hidden, `None` if they are visible
+    hidden_with: Option<WorkspaceSwitchStrategy>,
+    /// The positions of the windows before they got moved offscreen
+    offscreen_positions: HashMap<WindowId, Position>,
+    /// The area the workspace has to get rendered in once it is visible again, because its
+    /// layout changed while it was hidden
+    deferred_area: Option<Area>,
+    /// Whether the user named the workspace, which turns off the automatic naming
+    has_custom_name: bool,
 }
 
 pub enum WorkspaceError {
@@ -36,7 +75,87 @@ impl Workspace {
             layout_name: layout_name.to_string(),
             state: WorkspaceState::Normal,
             graph: Graph::new(),
+            floating_windows: Vec::new(),
+            persistent: false,
+            parked_windows: HashMap::new(),
+            swallowed_windows: HashMap::new(),
+            focus_history: Vec::new(),
+            urgent_windows: Vec::new(),
+            hidden_with: None,
+            offscreen_positions: HashMap::new(),
+            deferred_area: None,
+            has_custom_name: false,
+        }
+    }
+
+    /// The layout only changes while the workspace is empty, since the layout function of the
+    /// workspace keeps track of the windows it organized.
+    pub fn apply_definition(&mut self, definition: &WorkspaceDefinition) {
+        if let Some(name) = &definition.name {
+            self.set_name(name.clone());
         }
+
+        if let Some(layout) = &definition.layout {
+            if self.is_empty() {
+                self.layout_name = layout.clone();
+            }
+        }
+
+        self.persistent = definition.persistent;
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.display_name = name;
+        self.has_custom_name = true;
+    }
+
+    pub fn has_custom_name(&self) -> bool {
+        self.has_custom_name
+    }
+
+    /// Derives the name of the workspace from its windows, unless the user named it
+    pub fn update_auto_name(&mut self, config: &Config) {
+        if self.has_custom_name {
+            return;
+        }
+
+        let app_name = match config.workspace_naming {
+            WorkspaceNaming::Manual => None,
+            WorkspaceNaming::Focused => self
+                .get_last_focused_win(None)
+                .or_else(|| self.visible_windows().next())
+                .map(|id| config.get_app_name(&Window::new(id))),
+            WorkspaceNaming::Majority => {
+                // Applications are counted in the order their windows got focused, which makes
+                // the most recently focused application win a tie.
+                let mut counts: Vec<(String, usize)> = Vec::new();
+
+                for id in self
+                    .get_windows_by_mru()
+                    .into_iter()
+                    .chain(self.floating_windows.iter().copied())
+                {
+                    let app_name = config.get_app_name(&Window::new(id));
+
+                    match counts.iter_mut().find(|(name, _)| *name == app_name) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((app_name, 1)),
+                    }
+                }
+
+                let max_count = counts.iter().map(|(_, count)| *count).max();
+
+                counts
+                    .into_iter()
+                    .find(|(_, count)| Some(*count) == max_count)
+                    .map(|(name, _)| name)
+            }
+        };
+
+        self.display_name = match app_name {
+            Some(app_name) => format!("{} {}", self.id.0, app_name),
+            None => self.id.0.to_string(),
+        };
     }
 
     pub fn is_fullscreen(&self) -> bool {
@@ -48,43 +167,172 @@ impl Workspace {
             .get_focused_window_child(0)
             .and_then(|id| self.graph.get_node(id))
             .and_then(|n| n.try_get_window_id())
+            .filter(|id| !template::is_placeholder(*id))
             .map(Window::new)
     }
 
     pub fn is_empty(&self) -> bool {
         // If the graph doesn't have any edges then only the root node can exist.
         self.graph.edges.is_empty()
+            && self.floating_windows.is_empty()
+            && self.parked_windows.is_empty()
+            && self.swallowed_windows.is_empty()
     }
 
+    /// Includes the floating, parked and swallowed windows
     pub fn windows(&self) -> impl Iterator<Item = WindowId> + '_ {
+        self.visible_windows()
+            .chain(self.parked_windows.keys().copied())
+            .chain(self.swallowed_windows.values().copied())
+    }
+
+    /// The windows that are visible while the workspace is focused
+    pub fn visible_windows(&self) -> impl Iterator<Item = WindowId> + '_ {
         self.graph
             .nodes
             .values()
             .map(|n| n.try_get_window_id())
             .flatten()
+            .filter(|id| !template::is_placeholder(*id))
+            .chain(self.floating_windows.iter().copied())
+    }
+
+    /// The windows of the layout in depth-first order
+    pub fn get_windows_in_order(&self) -> Vec<WindowId> {
+        self.graph
+            .get_window_nodes_in_order()
+            .into_iter()
+            .filter_map(|id| self.graph.get_node(id))
+            .filter_map(|node| node.try_get_window_id())
+            .filter(|id| !template::is_placeholder(*id))
+            .collect()
+    }
+
+    pub fn is_floating(&self, id: WindowId) -> bool {
+        self.floating_windows.contains(&id)
+    }
+
+    pub fn is_parked(&self, id: WindowId) -> bool {
+        self.parked_windows.contains_key(&id)
+    }
+
+    pub fn is_swallowed(&self, id: WindowId) -> bool {
+        self.swallowed_windows.values().any(|x| *x == id)
+    }
+
+    pub fn is_urgent(&self) -> bool {
+        !self.urgent_windows.is_empty()
+    }
+
+    pub fn is_win_urgent(&self, id: WindowId) -> bool {
+        self.urgent_windows.contains(&id)
     }
 
-    pub fn hide(&self) {
-        for win in self.windows() {
-            Window::new(win).hide();
+    pub fn is_hidden(&self) -> bool {
+        self.hidden_with.is_some()
+    }
+
+    pub fn hide(&mut self, strategy: WorkspaceSwitchStrategy) {
+        if self.is_hidden() {
+            return;
+        }
+
+        let windows: Vec<WindowId> = self.visible_windows().collect();
+
+        for id in windows {
+            self.hide_window(id, strategy);
         }
+
+        self.hidden_with = Some(strategy);
     }
 
-    pub fn minimize(&self) {
-        for win in self.windows() {
-            Window::new(win).minimize();
+    fn hide_window(&mut self, id: WindowId, strategy: WorkspaceSwitchStrategy) {
+        let win = Window::new(id);
+
+        match strategy {
+            WorkspaceSwitchStrategy::Minimize => {
+                if !win.is_minimized() {
+                    window_event_loop::expect_event(id, WindowEventKind::Minimized);
+                }
+                win.minimize();
+            }
+            WorkspaceSwitchStrategy::Hide => {
+                // Hiding a window is reported as minimizing it
+                if win.is_visible() {
+                    window_event_loop::expect_event(id, WindowEventKind::Minimized);
+                }
+                win.hide();
+            }
+            WorkspaceSwitchStrategy::Offscreen => {
+                // The window might already be offscreen, in which case we have to keep the
+                // position it had before.
+                self.offscreen_positions
+                    .entry(id)
+                    .or_insert_with(|| win.get_position());
+                win.reposition(OFFSCREEN_POSITION);
+            }
         }
     }
 
-    pub fn unminimize(&self) {
-        for win in self.windows() {
-            Window::new(win).unminimize();
+    /// Shows a window that nog hid on its own (e.g. a swallowed window). In case the workspace is
+    /// hidden, the window gets hidden the same way as the other windows instead.
+    pub fn restore_window(&mut self, id: WindowId) {
+        Window::new(id).show();
+
+        if let Some(strategy) = self.hidden_with {
+            self.hide_window(id, strategy);
         }
     }
 
-    pub fn show(&self) {
-        for win in self.windows() {
-            Window::new(win).show();
+    /// Reverts what `hide` did, using the strategy the windows got hidden with
+    pub fn show(&mut self) {
+        let strategy = match self.hidden_with.take() {
+            Some(strategy) => strategy,
+            None => return,
+        };
+
+        let windows: Vec<WindowId> = self.visible_windows().collect();
+
+        for id in windows {
+            let win = Window::new(id);
+
+            match strategy {
+                WorkspaceSwitchStrategy::Minimize => {
+                    if win.is_minimized() {
+                        window_event_loop::expect_event(id, WindowEventKind::Restored);
+                    }
+                    win.unminimize();
+                }
+                WorkspaceSwitchStrategy::Hide => win.show(),
+                WorkspaceSwitchStrategy::Offscreen => {
+                    if let Some(pos) = self.offscreen_positions.remove(&id) {
+                        win.reposition(pos);
+                    }
+                }
+            }
+        }
+
+        self.offscreen_positions.clear();
+    }
+
+    /// Renders the workspace, unless it is hidden. Rendering a hidden workspace would reveal its
+    /// windows, so in that case the rendering happens once `show_deferred` gets called.
+    pub fn render_or_defer(&mut self, config: &DisplayConfigView, area: Area) {
+        if self.is_hidden() {
+            self.deferred_area = Some(area);
+        } else {
+            self.render(config, area);
+            self.graph.dirty = false;
+        }
+    }
+
+    /// Shows the workspace and renders it in case its layout changed while it was hidden
+    pub fn show_deferred(&mut self, config: &DisplayConfigView) {
+        self.show();
+
+        if let Some(area) = self.deferred_area.take() {
+            self.render(config, area);
+            self.graph.dirty = false;
         }
     }
 
@@ -96,9 +344,12 @@ impl Workspace {
 
     pub fn has_window(&self, id: WindowId) -> bool {
         self.graph.get_window_node(id).is_some()
+            || self.is_floating(id)
+            || self.is_parked(id)
+            || self.is_swallowed(id)
     }
 
-    pub fn render(&self, config: &Config, mut area: Area) {
+    pub fn render(&self, config: &DisplayConfigView, mut area: Area) {
         area.pos.x += config.outer_gap as isize;
         area.pos.y += config.outer_gap as isize;
 
@@ -149,9 +400,46 @@ impl Workspace {
 
         self.focus_node(node_id);
 
+        self.focus_history.retain(|(x, _)| *x != id);
+        self.focus_history.insert(0, (id, Instant::now()));
+        self.urgent_windows.retain(|x| *x != id);
+
         Ok(())
     }
 
+    pub fn forget_focus(&mut self, id: WindowId) {
+        self.focus_history.retain(|(x, _)| *x != id);
+    }
+
+    /// Returns the most recently focused window that is still part of the layout, ignoring
+    /// `except`
+    pub fn get_last_focused_win(&self, except: Option<WindowId>) -> Option<WindowId> {
+        self.focus_history
+            .iter()
+            .map(|(id, _)| *id)
+            .find(|id| Some(*id) != except && self.graph.get_window_node(*id).is_some())
+    }
+
+    /// The windows of the layout, beginning with the most recently focused one. Windows that
+    /// never got focused follow in depth-first order.
+    pub fn get_windows_by_mru(&self) -> Vec<WindowId> {
+        let in_order = self.get_windows_in_order();
+        let mut windows: Vec<WindowId> = self
+            .focus_history
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| in_order.contains(id))
+            .collect();
+
+        for id in in_order {
+            if !windows.contains(&id) {
+                windows.push(id);
+            }
+        }
+
+        windows
+    }
+
     pub fn focus_in_direction(&mut self, dir: Direction) -> Option<GraphNodeId> {
         self.graph
             .get_focused_window_child(0)
@@ -173,7 +461,7 @@ impl Workspace {
     }
 }
 
-fn render_node(id: GraphNodeId, graph: &Graph, config: &Config, mut area: Area) {
+fn render_node(id: GraphNodeId, graph: &Graph, config: &DisplayConfigView, mut area: Area) {
     let node = graph
         .get_node(id)
         .expect("Cannot render a node that doesn't exist");
@@ -209,6 +497,8 @@ fn render_node(id: GraphNodeId, graph: &Graph, config: &Config, mut area: Area)
                 }
             }
         }
+        // The slot of a placeholder stays empty until its window shows up
+        GraphNode::Window(win_id) if template::is_placeholder(*win_id) => {}
         GraphNode::Window(win_id) => {
             area.pos.x += config.inner_gap as isize;
             area.pos.y += config.inner_gap as isize;
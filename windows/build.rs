@@ -9,12 +9,16 @@ fn main() {
         Windows::Win32::UI::Accessibility::*,
         Windows::Win32::UI::Shell::*,
         Windows::Win32::System::Diagnostics::Debug::*,
+        Windows::Win32::System::Diagnostics::ToolHelp::*,
         Windows::Win32::Storage::StructuredStorage::*,
         Windows::Win32::System::LibraryLoader::*,
         Windows::Win32::System::SystemServices::*,
         Windows::Win32::System::Memory::*,
         Windows::Win32::System::Com::*,
         Windows::Win32::System::Threading::CreateProcessA,
+        Windows::Win32::System::Threading::GetProcessId,
+        Windows::Win32::System::Threading::OpenProcess,
+        Windows::Win32::System::Threading::QueryFullProcessImageNameW,
         Windows::Win32::System::WindowsProgramming::GetUserNameW
     );
 }